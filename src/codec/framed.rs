@@ -0,0 +1,177 @@
+use bytes::BytesMut;
+use std::{
+    io,
+    task::{Poll, ready},
+};
+
+use super::{Decoder, Encoder};
+use crate::io::{AsyncIoRead, AsyncIoWrite, BufCursor, BufReader};
+
+/// An io object with [`Decoder`] and [`Encoder`].
+///
+/// Reading from [`Framed`] yields decoded frames, and writing into [`Framed`] encode frames into
+/// an internal write buffer which is written to the underlying io on flush.
+///
+/// See [module level docs][super] for more details.
+#[derive(Debug)]
+pub struct Framed<IO, C> {
+    io: BufReader<IO>,
+    codec: C,
+    write_buf: BytesMut,
+}
+
+impl<IO, C> Framed<IO, C> {
+    /// Creates new [`Framed`].
+    #[inline]
+    pub fn new(io: IO, codec: C) -> Self {
+        Self {
+            io: BufReader::new(io),
+            codec,
+            write_buf: BytesMut::new(),
+        }
+    }
+
+    /// Returns reference to the codec.
+    #[inline]
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns mutable reference to the codec.
+    #[inline]
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Returns reference to the underlying io.
+    #[inline]
+    pub fn inner(&self) -> &IO {
+        self.io.inner()
+    }
+
+    /// Returns mutable reference to the underlying io.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut IO {
+        self.io.inner_mut()
+    }
+
+    /// Returns reference to the read buffer.
+    #[inline]
+    pub fn read_buffer(&self) -> &BytesMut {
+        self.io.bufffer()
+    }
+
+    /// Returns reference to the write buffer.
+    #[inline]
+    pub fn write_buffer(&self) -> &BytesMut {
+        &self.write_buf
+    }
+
+    /// Consumes the [`Framed`], returning the underlying io, codec, read buffer, and unflushed
+    /// write buffer.
+    pub fn into_parts(self) -> (IO, C, BytesMut, BytesMut) {
+        let (io, read_buf) = self.io.into_parts();
+        (io, self.codec, read_buf, self.write_buf)
+    }
+}
+
+impl<IO, C> Framed<IO, C>
+where
+    IO: AsyncIoRead,
+    C: Decoder,
+{
+    /// Poll for the next decoded frame.
+    ///
+    /// Returns `None` if the underlying io reached end of stream at frame boundary.
+    pub fn poll_next(&mut self, cx: &mut std::task::Context) -> Poll<Option<io::Result<C::Item>>> {
+        let mut cursor = BufCursor::new(&mut self.io);
+        let result = match ready!(self.codec.poll_decode(&mut cursor, cx)) {
+            Ok(Some(item)) => Some(Ok(item)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        };
+        Poll::Ready(result)
+    }
+
+    /// Returns the next decoded frame.
+    ///
+    /// Returns `None` if the underlying io reached end of stream at frame boundary.
+    #[inline]
+    pub fn read(&mut self) -> impl Future<Output = Option<io::Result<C::Item>>> {
+        std::future::poll_fn(|cx| self.poll_next(cx))
+    }
+}
+
+impl<IO, C> Framed<IO, C>
+where
+    IO: AsyncIoWrite,
+{
+    /// Encode a frame into the write buffer.
+    ///
+    /// Note that this does not write to the underlying io, use
+    /// [`poll_flush`][Framed::poll_flush] to write the buffer.
+    #[inline]
+    pub fn write<I>(&mut self, item: I) -> io::Result<()>
+    where
+        C: Encoder<I>,
+    {
+        self.codec.encode(item, &mut self.write_buf)
+    }
+
+    /// Write all data in the write buffer into the underlying io.
+    ///
+    /// Returns [`Poll::Pending`] if the underlying io not ready for writing.
+    #[inline]
+    pub fn poll_flush(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        self.io.inner().poll_write_all_buf(&mut self.write_buf, cx)
+    }
+
+    /// Write all data in the write buffer into the underlying io.
+    #[inline]
+    pub fn flush(&mut self) -> impl Future<Output = io::Result<()>> {
+        std::future::poll_fn(|cx| self.poll_flush(cx))
+    }
+
+    /// Encode a frame and write it to the underlying io.
+    ///
+    /// The frame is encoded when the future is first polled, if the future is dropped before
+    /// that, the frame is not written. Once encoded, the frame is in the write buffer and written
+    /// by the next flush even if the future is dropped.
+    pub fn send<I>(&mut self, item: I) -> impl Future<Output = io::Result<()>>
+    where
+        C: Encoder<I>,
+    {
+        let mut item = Some(item);
+        std::future::poll_fn(move |cx| {
+            if let Some(item) = item.take() {
+                self.write(item)?;
+            }
+            self.poll_flush(cx)
+        })
+    }
+}
+
+#[test]
+fn test_framed() {
+    use std::pin::pin;
+
+    use crate::{codec::LinesCodec, io::mock};
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .write(b"Foo\n")
+        .read(b"Bar\n")
+        .build();
+    let mut framed = Framed::new(io, LinesCodec::new());
+
+    // frame is encoded when the future is polled
+    drop(framed.send("Baz"));
+    assert!(framed.write_buffer().is_empty());
+
+    assert!(matches!(pin!(framed.send("Foo")).poll(&mut cx), Poll::Ready(Ok(()))));
+    assert!(framed.write_buffer().is_empty());
+
+    let frame = framed.poll_next(&mut cx);
+    assert!(matches!(frame, Poll::Ready(Some(Ok(l))) if l == "Bar"));
+    assert!(matches!(framed.poll_next(&mut cx), Poll::Ready(None)));
+}
@@ -0,0 +1,267 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{
+    io,
+    task::{Poll, ready},
+};
+
+use super::{Decoder, Encoder};
+use crate::io::{AsyncBufRead, BufCursor};
+
+const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+/// Length field type of [`LengthDelimitedCodec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthField {
+    /// `u8` length field.
+    U8,
+    /// `u16` big endian length field.
+    U16Be,
+    /// `u16` little endian length field.
+    U16Le,
+    /// `u32` big endian length field.
+    U32Be,
+    /// `u32` little endian length field.
+    U32Le,
+    /// `u64` big endian length field.
+    U64Be,
+    /// `u64` little endian length field.
+    U64Le,
+}
+
+impl LengthField {
+    /// Returns the length field size in bytes.
+    #[inline]
+    pub const fn width(&self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16Be | Self::U16Le => 2,
+            Self::U32Be | Self::U32Le => 4,
+            Self::U64Be | Self::U64Le => 8,
+        }
+    }
+
+    /// Returns the maximum length that can be represented.
+    #[inline]
+    pub const fn max_len(&self) -> u64 {
+        match self {
+            Self::U8 => u8::MAX as u64,
+            Self::U16Be | Self::U16Le => u16::MAX as u64,
+            Self::U32Be | Self::U32Le => u32::MAX as u64,
+            Self::U64Be | Self::U64Le => u64::MAX,
+        }
+    }
+
+    fn get(&self, mut buf: &[u8]) -> u64 {
+        match self {
+            Self::U8 => buf.get_u8() as u64,
+            Self::U16Be => buf.get_u16() as u64,
+            Self::U16Le => buf.get_u16_le() as u64,
+            Self::U32Be => buf.get_u32() as u64,
+            Self::U32Le => buf.get_u32_le() as u64,
+            Self::U64Be => buf.get_u64(),
+            Self::U64Le => buf.get_u64_le(),
+        }
+    }
+
+    fn put(&self, len: u64, dst: &mut BytesMut) {
+        match self {
+            Self::U8 => dst.put_u8(len as u8),
+            Self::U16Be => dst.put_u16(len as u16),
+            Self::U16Le => dst.put_u16_le(len as u16),
+            Self::U32Be => dst.put_u32(len as u32),
+            Self::U32Le => dst.put_u32_le(len as u32),
+            Self::U64Be => dst.put_u64(len),
+            Self::U64Le => dst.put_u64_le(len),
+        }
+    }
+}
+
+/// Length prefixed frame codec.
+///
+/// Each frame is prefixed with a length field, optionally preceded by a fixed size header.
+///
+/// ```text
+/// +-- header offset --+-- length field --+------- payload -------+
+/// |    0x01 0x02      |    0x00 0x03     |     0x41 0x42 0x43    |
+/// +-------------------+------------------+-----------------------+
+/// ```
+///
+/// The length field contains the payload length. The decoded frame contains the header followed
+/// by the payload, without the length field. When the header offset is `0`, which is the
+/// default, the frame is split from [`BufReader`][crate::io::BufReader] buffer without copying.
+///
+/// Encoding a frame does the reverse, the first header offset bytes of the frame is written
+/// before the length field.
+#[derive(Debug, Clone)]
+pub struct LengthDelimitedCodec {
+    field: LengthField,
+    offset: usize,
+    max_frame_size: usize,
+}
+
+impl LengthDelimitedCodec {
+    /// Creates new [`LengthDelimitedCodec`] with given length field type.
+    ///
+    /// Currently the default max frame size is 8 MiB.
+    #[inline]
+    pub fn new(field: LengthField) -> Self {
+        Self {
+            field,
+            offset: 0,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Returns the length field type.
+    #[inline]
+    pub fn length_field(&self) -> LengthField {
+        self.field
+    }
+
+    /// Returns the header size before the length field.
+    #[inline]
+    pub fn header_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Set the header size before the length field.
+    #[inline]
+    pub fn set_header_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    /// Returns the maximum payload size.
+    #[inline]
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Set the maximum payload size.
+    ///
+    /// Frame with larger payload will returns [`InvalidData`][io::ErrorKind::InvalidData] error
+    /// when decoding, and [`InvalidInput`][io::ErrorKind::InvalidInput] when encoding.
+    #[inline]
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+}
+
+impl Default for LengthDelimitedCodec {
+    #[inline]
+    fn default() -> Self {
+        Self::new(LengthField::U32Be)
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = Bytes;
+
+    fn poll_decode<B: AsyncBufRead>(
+        &mut self,
+        cursor: &mut BufCursor<B>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<Option<Bytes>>> {
+        if ready!(cursor.poll_is_eof(cx)?) {
+            return Poll::Ready(Ok(None));
+        }
+
+        let width = self.field.width();
+        let Some(head_len) = self.offset.checked_add(width) else {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "header offset is too large",
+            )));
+        };
+        let head = ready!(cursor.poll_peek(head_len, cx)?);
+        let len = self.field.get(&head[self.offset..]);
+
+        let frame_len = match usize::try_from(len) {
+            Ok(len) if len <= self.max_frame_size => head_len.checked_add(len),
+            _ => None,
+        };
+        let Some(frame_len) = frame_len else {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame size exceeds max frame size",
+            )));
+        };
+
+        let mut frame = ready!(cursor.poll_take_mut(frame_len, cx)?);
+
+        // remove the length field by moving the header forward
+        frame.copy_within(..self.offset, width);
+        frame.advance(width);
+
+        Poll::Ready(Ok(Some(frame.freeze())))
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for LengthDelimitedCodec {
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> io::Result<()> {
+        let Some((header, payload)) = item.as_ref().split_at_checked(self.offset) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame is shorter than header offset",
+            ));
+        };
+
+        if payload.len() > self.max_frame_size || payload.len() as u64 > self.field.max_len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame size exceeds max frame size",
+            ));
+        }
+
+        dst.reserve(header.len() + self.field.width() + payload.len());
+        dst.put_slice(header);
+        self.field.put(payload.len() as u64, dst);
+        dst.put_slice(payload);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_length_delimited() {
+    use crate::io::Chunks;
+
+    let mut codec = LengthDelimitedCodec::new(LengthField::U16Be);
+    let mut io = Chunks::new(&[b"\x00\x03Fo", b"", b"o\x00", b"\x00", b"\x00\x03Bar"]);
+
+    // incomplete frame is not consumed
+    assert!(io.poll_decode(&mut codec).is_pending());
+    assert_eq!(io.chunk(), b"\x00\x03Fo");
+
+    let frame = io.poll_decode(&mut codec);
+    assert!(matches!(frame, Poll::Ready(Ok(Some(f))) if f == "Foo"));
+    let frame = io.poll_decode(&mut codec);
+    assert!(matches!(frame, Poll::Ready(Ok(Some(f))) if f.is_empty()));
+    let frame = io.poll_decode(&mut codec);
+    assert!(matches!(frame, Poll::Ready(Ok(Some(f))) if f == "Bar"));
+    assert!(matches!(io.poll_decode(&mut codec), Poll::Ready(Ok(None))));
+
+    // header offset and little endian
+    let mut codec = LengthDelimitedCodec::new(LengthField::U32Le);
+    codec.set_header_offset(1);
+    codec.set_max_frame_size(4);
+
+    let mut buf = BytesMut::new();
+    codec.encode("TBaz", &mut buf).unwrap();
+    assert_eq!(buf, &b"T\x03\x00\x00\x00Baz"[..]);
+    assert!(codec.encode("TFoo-Bar", &mut buf).is_err());
+
+    let mut io = Chunks::new(&[b"T\x03\x00\x00\x00Baz", b"T\x05\x00\x00\x00"]);
+    let frame = io.poll_decode(&mut codec);
+    assert!(matches!(frame, Poll::Ready(Ok(Some(f))) if f == "TBaz"));
+    let frame = io.poll_decode(&mut codec);
+    assert!(matches!(frame, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::InvalidData));
+
+    // frame size overflow
+    let mut codec = LengthDelimitedCodec::new(LengthField::U64Be);
+    codec.set_header_offset(1);
+    codec.set_max_frame_size(usize::MAX);
+
+    let mut io = Chunks::new(&[b"T\xff\xff\xff\xff\xff\xff\xff\xff"]);
+    let frame = io.poll_decode(&mut codec);
+    assert!(matches!(frame, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::InvalidData));
+}
@@ -0,0 +1,237 @@
+use bytes::{BufMut, BytesMut};
+use std::{
+    io,
+    task::{Poll, ready},
+};
+
+use super::{Decoder, Encoder};
+use crate::{
+    ByteStr,
+    io::{AsyncBufRead, BufCursor},
+};
+
+/// Line delimited codec.
+///
+/// Each frame is delimited by `\n` or `\r\n`, the delimiter is not included in the decoded
+/// frame. When the underlying io reached end of stream, the remaining data is decoded as the last
+/// line.
+///
+/// Encoding a frame appends `\n` to the given line.
+#[derive(Debug, Clone)]
+pub struct LinesCodec {
+    max_length: usize,
+    /// Position in the buffer which already searched for delimiter.
+    next_index: usize,
+    /// Discarding the rest of a line which exceeds the max length.
+    is_discarding: bool,
+}
+
+impl LinesCodec {
+    /// Creates new [`LinesCodec`] without line length limit.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_max_length(usize::MAX)
+    }
+
+    /// Creates new [`LinesCodec`] with maximum line length.
+    ///
+    /// Line longer than `max_length` will returns [`InvalidData`][io::ErrorKind::InvalidData]
+    /// error when decoding, the line is discarded and the next decode continues from the next
+    /// line.
+    #[inline]
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self { max_length, next_index: 0, is_discarding: false }
+    }
+
+    /// Returns the maximum line length.
+    #[inline]
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    fn split_line<B: AsyncBufRead>(
+        &mut self,
+        cursor: &mut BufCursor<B>,
+        len: usize,
+        consume: usize,
+    ) -> io::Result<ByteStr> {
+        self.next_index = 0;
+
+        cursor.consume(consume);
+
+        let mut line = cursor.commit_split();
+        line.truncate(len);
+        // `\r` of the unterminated last line is not part of the delimiter
+        if consume > len && line.last() == Some(&b'\r') {
+            line.truncate(len - 1);
+        }
+
+        ByteStr::from_utf8(line.freeze())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Discard `len` bytes of a line which exceeds the max length, returns the error.
+    ///
+    /// If `is_discarding` is `true`, the rest of the line is discarded by the next decode.
+    fn discard<B: AsyncBufRead>(
+        &mut self,
+        cursor: &mut BufCursor<B>,
+        len: usize,
+        is_discarding: bool,
+    ) -> io::Error {
+        self.next_index = 0;
+        self.is_discarding = is_discarding;
+
+        cursor.consume(len);
+        cursor.commit();
+
+        max_length_exceeded()
+    }
+}
+
+impl Default for LinesCodec {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = ByteStr;
+
+    fn poll_decode<B: AsyncBufRead>(
+        &mut self,
+        cursor: &mut BufCursor<B>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<Option<ByteStr>>> {
+        loop {
+            let chunk = cursor.chunk();
+            let start = self.next_index.min(chunk.len());
+            let newline = chunk[start..].iter().position(|&b| b == b'\n').map(|n| start + n);
+
+            if self.is_discarding {
+                match newline {
+                    Some(len) => {
+                        self.is_discarding = false;
+                        cursor.consume(len + 1);
+                    }
+                    None => {
+                        let len = chunk.len();
+                        cursor.consume(len);
+                    }
+                }
+                cursor.commit();
+                self.next_index = 0;
+
+                if self.is_discarding && ready!(cursor.poll_read_fill(cx)?) == 0 {
+                    self.is_discarding = false;
+                    return Poll::Ready(Ok(None));
+                }
+                continue;
+            }
+
+            if let Some(len) = newline {
+                if line_length(&chunk[..len]) > self.max_length {
+                    return Poll::Ready(Err(self.discard(cursor, len + 1, false)));
+                }
+                return Poll::Ready(self.split_line(cursor, len, len + 1).map(Some));
+            }
+
+            // the trailing `\r` may be part of the delimiter
+            if line_length(chunk) > self.max_length {
+                let len = chunk.len();
+                return Poll::Ready(Err(self.discard(cursor, len, true)));
+            }
+
+            self.next_index = chunk.len();
+
+            if ready!(cursor.poll_read_fill(cx)?) == 0 {
+                let len = cursor.chunk().len();
+                if len == 0 {
+                    self.next_index = 0;
+                    return Poll::Ready(Ok(None));
+                }
+                if len > self.max_length {
+                    return Poll::Ready(Err(self.discard(cursor, len, false)));
+                }
+                return Poll::Ready(self.split_line(cursor, len, len).map(Some));
+            }
+        }
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for LinesCodec {
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> io::Result<()> {
+        let line = item.as_ref();
+        dst.reserve(line.len() + 1);
+        dst.put_slice(line.as_bytes());
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
+
+/// Returns the line length excluding the trailing `\r`.
+fn line_length(line: &[u8]) -> usize {
+    match line.last() {
+        Some(b'\r') => line.len() - 1,
+        _ => line.len(),
+    }
+}
+
+fn max_length_exceeded() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "line length exceeds max length")
+}
+
+#[test]
+fn test_lines() {
+    use crate::io::Chunks;
+
+    let mut codec = LinesCodec::with_max_length(8);
+    let mut io = Chunks::new(&[b"Foo\nBa", b"", b"r\r\n\n", b"Baz"]);
+
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l == "Foo"));
+
+    // incomplete line is not consumed
+    assert!(io.poll_decode(&mut codec).is_pending());
+    assert_eq!(io.chunk(), b"Ba");
+
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l == "Bar"));
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l.is_empty()));
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l == "Baz"));
+    assert!(matches!(io.poll_decode(&mut codec), Poll::Ready(Ok(None))));
+
+    let mut io = Chunks::new(&[b"Content-", b"Type\n"]);
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::InvalidData));
+
+    // max length does not include the delimiter
+    let mut io = Chunks::new(&[b"FooBarBa\r\n", b"FooBarBa\r", b"", b"\n", b"FooBarBaz\r\n"]);
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l == "FooBarBa"));
+    assert!(io.poll_decode(&mut codec).is_pending());
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l == "FooBarBa"));
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::InvalidData));
+
+    // too long line is discarded until the next line
+    let mut io = Chunks::new(&[b"FooBarBazQux", b"", b"Qux\nFoo\n", b"Bar\r"]);
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::InvalidData));
+    assert!(io.poll_decode(&mut codec).is_pending());
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l == "Foo"));
+
+    // trailing `\r` of the unterminated last line is kept
+    let line = io.poll_decode(&mut codec);
+    assert!(matches!(line, Poll::Ready(Ok(Some(l))) if l == "Bar\r"));
+    assert!(matches!(io.poll_decode(&mut codec), Poll::Ready(Ok(None))));
+
+    let mut buf = BytesMut::new();
+    codec.encode("Foo", &mut buf).unwrap();
+    assert_eq!(buf, &b"Foo\n"[..]);
+}
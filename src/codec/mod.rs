@@ -0,0 +1,98 @@
+//! Framing codec.
+//!
+//! [`Decoder`] parse frames from an [`AsyncBufRead`] through a [`BufCursor`], and [`Encoder`]
+//! serialize frames into a buffer. [`Framed`] combine both over a single io object.
+//!
+//! # Decoding
+//!
+//! [`Decoder`] follows the stateless parsing guarantee described in `docs/parsing.md`: if the
+//! decoding is incomplete, either by an error or pending, the underlying io buffer will not be
+//! advanced. Decoder only calls [`BufCursor::commit`] when a full frame is decoded.
+//!
+//! ```
+//! # use std::{io, task::{Poll, ready}};
+//! use tcio::codec::Decoder;
+//! use tcio::io::{AsyncBufRead, BufCursor};
+//!
+//! /// Decode a fixed 4 bytes big endian integer.
+//! struct U32Codec;
+//!
+//! impl Decoder for U32Codec {
+//!     type Item = u32;
+//!
+//!     fn poll_decode<B: AsyncBufRead>(
+//!         &mut self,
+//!         cursor: &mut BufCursor<B>,
+//!         cx: &mut std::task::Context,
+//!     ) -> Poll<io::Result<Option<u32>>> {
+//!         if ready!(cursor.poll_is_eof(cx)?) {
+//!             return Poll::Ready(Ok(None));
+//!         }
+//!
+//!         let chunk = ready!(cursor.poll_get(4, cx)?);
+//!         let value = u32::from_be_bytes(chunk.try_into().unwrap());
+//!
+//!         cursor.commit();
+//!         Poll::Ready(Ok(Some(value)))
+//!     }
+//! }
+//! ```
+mod framed;
+mod length;
+mod lines;
+
+pub use framed::Framed;
+pub use length::{LengthDelimitedCodec, LengthField};
+pub use lines::LinesCodec;
+
+use bytes::BytesMut;
+use std::{io, task::Poll};
+
+use crate::io::{AsyncBufRead, BufCursor};
+
+/// Decode frames from an [`AsyncBufRead`].
+///
+/// See [module level docs][self] for more details.
+pub trait Decoder {
+    /// The type of decoded frames.
+    type Item;
+
+    /// Attempts to decode a frame from the given cursor.
+    ///
+    /// Returns [`Poll::Pending`] if more data is required to decode a full frame, the cursor will
+    /// be discarded, and decoding will be retried from the last commit.
+    ///
+    /// Returns `Ok(None)` if the underlying io reached end of stream at frame boundary.
+    ///
+    /// Implementor must call [`BufCursor::commit`] only when a full frame is successfully
+    /// decoded.
+    fn poll_decode<B: AsyncBufRead>(
+        &mut self,
+        cursor: &mut BufCursor<B>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<Option<Self::Item>>>;
+}
+
+impl<D: Decoder> Decoder for &mut D {
+    type Item = D::Item;
+
+    fn poll_decode<B: AsyncBufRead>(
+        &mut self,
+        cursor: &mut BufCursor<B>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<Option<Self::Item>>> {
+        D::poll_decode(self, cursor, cx)
+    }
+}
+
+/// Encode frames into a buffer.
+pub trait Encoder<Item> {
+    /// Encode a frame into the given buffer.
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> io::Result<()>;
+}
+
+impl<Item, E: Encoder<Item>> Encoder<Item> for &mut E {
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> io::Result<()> {
+        E::encode(self, item, dst)
+    }
+}
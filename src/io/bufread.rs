@@ -1,5 +1,5 @@
 use std::{io, task::Poll};
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 
 use crate::io::AsyncIoRead;
 
@@ -24,6 +24,19 @@ pub trait AsyncBufRead {
     /// The `cnt` must be <= the number of bytes in the buffer returned
     /// [`chunk`][AsyncBufRead::chunk].
     fn consume(&mut self, cnt: usize);
+
+    /// Remove `cnt` data from the internal buffer, and returns it.
+    ///
+    /// The default implementation copies the data, [`BufReader`] split its internal buffer
+    /// without copying.
+    ///
+    /// The `cnt` must be <= the number of bytes in the buffer returned
+    /// [`chunk`][AsyncBufRead::chunk].
+    fn split_to(&mut self, cnt: usize) -> BytesMut {
+        let data = BytesMut::from(&self.chunk()[..cnt]);
+        self.consume(cnt);
+        data
+    }
 }
 
 impl<T: AsyncBufRead> AsyncBufRead for &mut T {
@@ -38,26 +51,74 @@ impl<T: AsyncBufRead> AsyncBufRead for &mut T {
     fn consume(&mut self, cnt: usize) {
         T::consume(self, cnt);
     }
+
+    fn split_to(&mut self, cnt: usize) -> BytesMut {
+        T::split_to(self, cnt)
+    }
 }
 
 /// An implementation of [`AsyncBufRead`] with given [`AsyncIoRead`].
+///
+/// Before reading from the underlying io, [`BufReader`] reserve at least the minimum read size
+/// of spare capacity, which by default is 1 KiB.
+///
+/// The internal buffer can be limited with [`set_max_buffer_size`][BufReader::set_max_buffer_size],
+/// by default the buffer is unbounded. When the internal buffer is full,
+/// [`poll_read_fill`][AsyncBufRead::poll_read_fill] returns
+/// [`QuotaExceeded`][io::ErrorKind::QuotaExceeded] error.
 #[derive(Debug)]
 pub struct BufReader<IO> {
     io: IO,
     buf: BytesMut,
+    min_read_size: usize,
+    max_buffer_size: usize,
 }
 
+const DEFAULT_MIN_READ_SIZE: usize = 0x0400;
+
 impl<IO> BufReader<IO> {
     /// Creates a new [`BufReader`].
     #[inline]
     pub fn new(io: IO) -> Self {
-        Self { io, buf: BytesMut::new() }
+        Self::with_capacity(io, 0)
     }
 
     /// Creates a new [`BufReader`] with the specified internal buffer capacity.
     #[inline]
     pub fn with_capacity(io: IO, capacity: usize) -> Self {
-        Self { io, buf: BytesMut::with_capacity(capacity) }
+        Self {
+            io,
+            buf: BytesMut::with_capacity(capacity),
+            min_read_size: DEFAULT_MIN_READ_SIZE,
+            max_buffer_size: usize::MAX,
+        }
+    }
+
+    /// Returns the minimum spare capacity reserved before reading.
+    #[inline]
+    pub fn min_read_size(&self) -> usize {
+        self.min_read_size
+    }
+
+    /// Set the minimum spare capacity reserved before reading.
+    #[inline]
+    pub fn set_min_read_size(&mut self, min_read_size: usize) {
+        self.min_read_size = min_read_size;
+    }
+
+    /// Returns the maximum internal buffer size.
+    #[inline]
+    pub fn max_buffer_size(&self) -> usize {
+        self.max_buffer_size
+    }
+
+    /// Set the maximum internal buffer size.
+    ///
+    /// When the internal buffer is full, [`poll_read_fill`][AsyncBufRead::poll_read_fill] returns
+    /// [`QuotaExceeded`][io::ErrorKind::QuotaExceeded] error.
+    #[inline]
+    pub fn set_max_buffer_size(&mut self, max_buffer_size: usize) {
+        self.max_buffer_size = max_buffer_size;
     }
 
     /// Returns a byte slice of the internal buffer.
@@ -89,15 +150,32 @@ impl<IO> BufReader<IO> {
     pub fn inner_mut(&mut self) -> &mut IO {
         &mut self.io
     }
+
+    /// Consumes the [`BufReader`], returning the underlying io and the unconsumed buffer.
+    #[inline]
+    pub fn into_parts(self) -> (IO, BytesMut) {
+        (self.io, self.buf)
+    }
 }
 
 impl<IO> AsyncBufRead for BufReader<IO>
 where
     IO: AsyncIoRead
 {
-    #[inline]
     fn poll_read_fill(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<usize>> {
-        self.io.poll_read_buf(&mut self.buf, cx)
+        let remaining = self.max_buffer_size.saturating_sub(self.buf.len());
+        if remaining == 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::QuotaExceeded,
+                "buffer size exceeds max buffer size",
+            )));
+        }
+
+        if self.buf.capacity() - self.buf.len() < self.min_read_size {
+            self.buf.reserve(self.min_read_size.min(remaining));
+        }
+
+        self.io.poll_read_buf(&mut (&mut self.buf).limit(remaining), cx)
     }
 
     #[inline]
@@ -109,5 +187,54 @@ where
     fn consume(&mut self, cnt: usize) {
         self.buf.advance(cnt);
     }
+
+    #[inline]
+    fn split_to(&mut self, cnt: usize) -> BytesMut {
+        self.buf.split_to(cnt)
+    }
 }
 
+
+#[test]
+fn test_buf_reader_limit() {
+    /// Infinite stream of zeros.
+    struct Zeros;
+
+    impl AsyncIoRead for Zeros {
+        fn poll_read_ready(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+            buf.fill(0);
+            Ok(buf.len())
+        }
+
+        fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+            match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+                Some(buf) => self.try_read(buf),
+                None => Ok(0),
+            }
+        }
+    }
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let mut io = BufReader::new(Zeros);
+    io.set_min_read_size(4);
+    io.set_max_buffer_size(10);
+
+    assert!(matches!(io.poll_read_fill(&mut cx), Poll::Ready(Ok(n)) if n >= 4));
+
+    // read is limited by max buffer size
+    while io.chunk().len() < 10 {
+        assert!(matches!(io.poll_read_fill(&mut cx), Poll::Ready(Ok(_))));
+    }
+    assert_eq!(io.chunk().len(), 10);
+
+    let err = io.poll_read_fill(&mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::QuotaExceeded));
+
+    io.consume(4);
+    assert!(matches!(io.poll_read_fill(&mut cx), Poll::Ready(Ok(4))));
+    assert_eq!(io.chunk().len(), 10);
+}
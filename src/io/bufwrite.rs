@@ -0,0 +1,440 @@
+use bytes::{Buf, BufMut, BytesMut, buf::UninitSlice};
+use std::{
+    io,
+    task::{Poll, ready},
+};
+
+use crate::io::{AsyncIoRead, AsyncIoWrite};
+
+const DEFAULT_CAPACITY: usize = 0x2000;
+
+/// A writer which bytes is written directly into its internal buffer.
+///
+/// This is the write counterpart of [`AsyncBufRead`][super::AsyncBufRead]. Writing is done in
+/// three steps:
+///
+/// 1. [`poll_reserve`][AsyncBufWrite::poll_reserve] ensure the internal buffer has spare capacity
+///    of at least `len` bytes, which may write the buffered bytes to make room.
+/// 2. [`chunk_mut`][AsyncBufWrite::chunk_mut] returns the spare capacity, caller write the bytes
+///    into it.
+/// 3. [`advance_mut`][AsyncBufWrite::advance_mut] commit the written bytes, which is then
+///    buffered until the next flush or reserve.
+///
+/// Bytes written into [`chunk_mut`][AsyncBufWrite::chunk_mut] without
+/// [`advance_mut`][AsyncBufWrite::advance_mut] is not buffered and may be overwritten. Buffered
+/// bytes is only guaranteed to be written after [`poll_flush`][AsyncBufWrite::poll_flush]
+/// returns ready.
+///
+/// # Examples
+///
+/// ```
+/// # use std::{io, task::{Poll, ready}};
+/// use tcio::io::AsyncBufWrite;
+///
+/// fn poll_encode<W: AsyncBufWrite>(
+///     mut io: W,
+///     cx: &mut std::task::Context,
+/// ) -> Poll<io::Result<()>> {
+///     ready!(io.poll_reserve(4, cx)?);
+///
+///     let chunk = io.chunk_mut();
+///     chunk[..4].copy_from_slice(&1024u32.to_be_bytes());
+///
+///     // SAFETY: 4 bytes is initialized above
+///     unsafe { io.advance_mut(4) };
+///
+///     Poll::Ready(Ok(()))
+/// }
+/// ```
+pub trait AsyncBufWrite {
+    /// Attempts to write all data in the internal buffer into the underlying io.
+    fn poll_flush(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>>;
+
+    /// Attempts to reserve capacity for at least `len` more bytes in the internal buffer.
+    ///
+    /// The internal buffer may be flushed to make room for the reserved bytes.
+    fn poll_reserve(&mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<()>>;
+
+    /// Returns the spare capacity of the internal buffer.
+    ///
+    /// The returned slice is at least `len` bytes long after a successful
+    /// [`poll_reserve`][AsyncBufWrite::poll_reserve].
+    fn chunk_mut(&mut self) -> &mut UninitSlice;
+
+    /// Commit `cnt` bytes written into [`chunk_mut`][AsyncBufWrite::chunk_mut].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the first `cnt` bytes of the slice returned by
+    /// [`chunk_mut`][AsyncBufWrite::chunk_mut] have been initialized.
+    unsafe fn advance_mut(&mut self, cnt: usize);
+}
+
+impl<T: AsyncBufWrite> AsyncBufWrite for &mut T {
+    fn poll_flush(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        T::poll_flush(self, cx)
+    }
+
+    fn poll_reserve(&mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        T::poll_reserve(self, len, cx)
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        T::chunk_mut(self)
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        unsafe { T::advance_mut(self, cnt) }
+    }
+}
+
+/// An implementation of [`AsyncBufWrite`] with given [`AsyncIoWrite`].
+///
+/// Small writes are collected into the internal buffer, and only written to the underlying io
+/// when the buffer would exceed the high-water mark, or when explicitly flushed with
+/// [`poll_flush`][BufWriter::poll_flush].
+///
+/// Writes larger than the high-water mark bypass the buffer. If the underlying io support
+/// vectored write, the buffered data and the large write is submitted in a single call.
+///
+/// Note that any buffered data that is not flushed will be lost when [`BufWriter`] is dropped.
+#[derive(Debug)]
+pub struct BufWriter<IO> {
+    io: IO,
+    buf: BytesMut,
+    high_water_mark: usize,
+}
+
+impl<IO> BufWriter<IO> {
+    /// Creates a new [`BufWriter`].
+    ///
+    /// Currently the default high-water mark is 8 KiB.
+    #[inline]
+    pub fn new(io: IO) -> Self {
+        Self::with_capacity(io, DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new [`BufWriter`] with the specified internal buffer capacity.
+    ///
+    /// The high-water mark is set to `capacity`.
+    #[inline]
+    pub fn with_capacity(io: IO, capacity: usize) -> Self {
+        Self {
+            io,
+            buf: BytesMut::with_capacity(capacity),
+            high_water_mark: capacity,
+        }
+    }
+
+    /// Returns the high-water mark.
+    #[inline]
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    /// Set the high-water mark.
+    ///
+    /// When buffered data would exceed the high-water mark, the buffer is flushed first.
+    #[inline]
+    pub fn set_high_water_mark(&mut self, high_water_mark: usize) {
+        self.high_water_mark = high_water_mark;
+    }
+
+    /// Returns a byte slice of the internal buffer.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns reference to the underlying buffer.
+    #[inline]
+    pub fn buffer(&self) -> &BytesMut {
+        &self.buf
+    }
+
+    /// Returns mutable reference to the underlying buffer.
+    #[inline]
+    pub fn buffer_mut(&mut self) -> &mut BytesMut {
+        &mut self.buf
+    }
+
+    /// Returns reference to the underlying io.
+    #[inline]
+    pub fn inner(&self) -> &IO {
+        &self.io
+    }
+
+    /// Returns mutable reference to the underlying io.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    /// Consumes the [`BufWriter`], returning the underlying io and the unflushed buffer.
+    #[inline]
+    pub fn into_parts(self) -> (IO, BytesMut) {
+        (self.io, self.buf)
+    }
+}
+
+impl<IO> BufWriter<IO>
+where
+    IO: AsyncIoWrite,
+{
+    /// Write a buffer into the [`BufWriter`], returning how many bytes were written.
+    ///
+    /// Returns [`Poll::Pending`] if the internal buffer needs to be flushed and the underlying
+    /// io not ready for writing.
+    pub fn poll_write(&mut self, buf: &[u8], cx: &mut std::task::Context) -> Poll<io::Result<usize>> {
+        if self.buf.len() + buf.len() <= self.high_water_mark {
+            self.buf.extend_from_slice(buf);
+            return Poll::Ready(Ok(buf.len()));
+        }
+
+        if buf.len() < self.high_water_mark {
+            ready!(self.poll_flush(cx)?);
+            self.buf.extend_from_slice(buf);
+            return Poll::Ready(Ok(buf.len()));
+        }
+
+        if !self.io.is_write_vectored() {
+            ready!(self.poll_flush(cx)?);
+            return self.io.poll_write(buf, cx);
+        }
+
+        // large write with pending buffered data, submit both in one call
+        while !self.buf.is_empty() {
+            let slices = [io::IoSlice::new(&self.buf), io::IoSlice::new(buf)];
+            let read = ready!(self.io.poll_write_vectored(&slices, cx)?);
+            if read == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+
+            let buffered = self.buf.len();
+            if read < buffered {
+                self.buf.advance(read);
+            } else {
+                self.buf.clear();
+                if read > buffered {
+                    return Poll::Ready(Ok(read - buffered));
+                }
+            }
+        }
+
+        self.io.poll_write(buf, cx)
+    }
+
+    /// Write all data from the provided buffer into the [`BufWriter`], advancing buffer cursor.
+    ///
+    /// Returns [`Poll::Pending`] if the internal buffer needs to be flushed and the underlying
+    /// io not ready for writing.
+    pub fn poll_write_all_buf<B>(
+        &mut self,
+        buf: &mut B,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<()>>
+    where
+        B: Buf + ?Sized,
+    {
+        while buf.has_remaining() {
+            let read = ready!(self.poll_write(buf.chunk(), cx)?);
+            buf.advance(read);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Write all internally buffered data into the underlying io.
+    ///
+    /// Returns [`Poll::Pending`] if the underlying io not ready for writing.
+    #[inline]
+    pub fn poll_flush(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        self.io.poll_write_all_buf(&mut self.buf, cx)
+    }
+
+    /// Write all data from the provided buffer into the [`BufWriter`], advancing buffer cursor.
+    #[inline]
+    pub fn write_all_buf<B>(&mut self, buf: &mut B) -> impl Future<Output = io::Result<()>>
+    where
+        B: Buf + ?Sized,
+    {
+        std::future::poll_fn(|cx| self.poll_write_all_buf(buf, cx))
+    }
+
+    /// Write all internally buffered data into the underlying io.
+    #[inline]
+    pub fn flush(&mut self) -> impl Future<Output = io::Result<()>> {
+        std::future::poll_fn(|cx| self.poll_flush(cx))
+    }
+
+    /// Write all internally buffered data and shuts down the write side of the underlying io.
+    ///
+    /// Returns [`Poll::Pending`] if the underlying io not ready for writing.
+    pub fn poll_shutdown(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush(cx)?);
+        self.io.poll_shutdown(cx)
+    }
+
+    /// Write all internally buffered data and shuts down the write side of the underlying io.
+    #[inline]
+    pub fn shutdown(&mut self) -> impl Future<Output = io::Result<()>> {
+        std::future::poll_fn(|cx| self.poll_shutdown(cx))
+    }
+}
+
+impl<IO> AsyncBufWrite for BufWriter<IO>
+where
+    IO: AsyncIoWrite,
+{
+    #[inline]
+    fn poll_flush(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        BufWriter::poll_flush(self, cx)
+    }
+
+    fn poll_reserve(&mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        if !self.buf.is_empty() && self.buf.len() + len > self.high_water_mark {
+            ready!(BufWriter::poll_flush(self, cx)?);
+        }
+        self.buf.reserve(len);
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        BufMut::chunk_mut(&mut self.buf)
+    }
+
+    #[inline]
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        unsafe { BufMut::advance_mut(&mut self.buf, cnt) }
+    }
+}
+
+impl<IO> AsyncIoRead for BufWriter<IO>
+where
+    IO: AsyncIoRead,
+{
+    #[inline]
+    fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        self.io.poll_read_ready(cx)
+    }
+
+    #[inline]
+    fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.io.try_read(buf)
+    }
+
+    #[inline]
+    fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.io.try_read_vectored(bufs)
+    }
+}
+
+#[test]
+fn test_buf_writer_coalesce() {
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct Io {
+        written: RefCell<Vec<u8>>,
+        calls: RefCell<usize>,
+    }
+
+    impl AsyncIoWrite for Io {
+        fn poll_write_ready(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+            *self.calls.borrow_mut() += 1;
+            self.written.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+            *self.calls.borrow_mut() += 1;
+            let mut written = self.written.borrow_mut();
+            for buf in bufs {
+                written.extend_from_slice(buf);
+            }
+            Ok(bufs.iter().map(|e| e.len()).sum())
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            true
+        }
+
+        fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let mut io = BufWriter::with_capacity(Io::default(), 8);
+
+    assert!(io.poll_write(b"Con", &mut cx).is_ready());
+    assert!(io.poll_write(b"tent", &mut cx).is_ready());
+    assert_eq!(io.buffer(), &b"Content"[..]);
+    assert_eq!(*io.inner().calls.borrow(), 0);
+
+    // exceeding high-water mark flush the buffer first
+    assert!(io.poll_write(b"-Type", &mut cx).is_ready());
+    assert_eq!(io.buffer(), &b"-Type"[..]);
+    assert_eq!(&io.inner().written.borrow()[..], b"Content");
+    assert_eq!(*io.inner().calls.borrow(), 1);
+
+    // large write submitted with buffered data in single vectored write
+    assert!(matches!(io.poll_write(b": text/html", &mut cx), Poll::Ready(Ok(11))));
+    assert!(io.buffer().is_empty());
+    assert_eq!(&io.inner().written.borrow()[..], b"Content-Type: text/html");
+    assert_eq!(*io.inner().calls.borrow(), 2);
+
+    assert!(io.poll_write(b"\r\n", &mut cx).is_ready());
+    assert!(io.poll_flush(&mut cx).is_ready());
+    assert_eq!(&io.inner().written.borrow()[..], b"Content-Type: text/html\r\n");
+    assert_eq!(*io.inner().calls.borrow(), 3);
+}
+
+#[test]
+fn test_buf_write() {
+    use crate::io::mock;
+
+    fn put_u32(
+        mut io: impl AsyncBufWrite,
+        value: u32,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<()>> {
+        ready!(io.poll_reserve(4, cx)?);
+        let chunk = io.chunk_mut();
+        assert!(chunk.len() >= 4);
+        chunk[..4].copy_from_slice(&value.to_be_bytes());
+        // SAFETY: 4 bytes is initialized above
+        unsafe { io.advance_mut(4) };
+        Poll::Ready(Ok(()))
+    }
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .write(b"\0\0\0\x01\0\0\0\x02")
+        .write(b"\0\0\0\x03")
+        .build();
+    let mut io = BufWriter::with_capacity(io, 8);
+
+    // `&mut T` writes into the same buffer
+    assert!(matches!(put_u32(&mut io, 1, &mut cx), Poll::Ready(Ok(()))));
+    assert!(matches!(put_u32(&mut io, 2, &mut cx), Poll::Ready(Ok(()))));
+    assert_eq!(io.buffer(), &b"\0\0\0\x01\0\0\0\x02"[..]);
+
+    // reserve write the buffered bytes when it would exceed the high-water mark
+    assert!(matches!(put_u32(&mut io, 3, &mut cx), Poll::Ready(Ok(()))));
+    assert_eq!(io.buffer(), &b"\0\0\0\x03"[..]);
+
+    // reserve larger than the high-water mark
+    assert!(matches!(io.poll_reserve(16, &mut cx), Poll::Ready(Ok(()))));
+    assert!(io.buffer().is_empty());
+    assert!(AsyncBufWrite::chunk_mut(&mut io).len() >= 16);
+
+    // bytes is not buffered until advanced
+    AsyncBufWrite::chunk_mut(&mut io)[..1].copy_from_slice(b"X");
+    assert!(io.buffer().is_empty());
+    assert!(matches!(AsyncBufWrite::poll_flush(&mut &mut io, &mut cx), Poll::Ready(Ok(()))));
+}
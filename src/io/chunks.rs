@@ -0,0 +1,49 @@
+use bytes::BytesMut;
+use std::{collections::VecDeque, io, task::Poll};
+
+use crate::io::{AsyncBufRead, BufCursor};
+
+/// Scripted [`AsyncBufRead`], each fill append the next chunk, empty chunk returns pending.
+pub(crate) struct Chunks {
+    buf: BytesMut,
+    chunks: VecDeque<&'static [u8]>,
+}
+
+impl Chunks {
+    pub(crate) fn new(chunks: &[&'static [u8]]) -> Self {
+        Self { buf: BytesMut::new(), chunks: chunks.iter().copied().collect() }
+    }
+
+    pub(crate) fn poll_decode<D>(&mut self, decoder: &mut D) -> Poll<io::Result<Option<D::Item>>>
+    where
+        D: crate::codec::Decoder,
+    {
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        decoder.poll_decode(&mut BufCursor::new(self), &mut cx)
+    }
+}
+
+impl AsyncBufRead for Chunks {
+    fn poll_read_fill(&mut self, _: &mut std::task::Context) -> Poll<io::Result<usize>> {
+        match self.chunks.pop_front() {
+            Some(b"") => Poll::Pending,
+            Some(chunk) => {
+                self.buf.extend_from_slice(chunk);
+                Poll::Ready(Ok(chunk.len()))
+            }
+            None => Poll::Ready(Ok(0)),
+        }
+    }
+
+    fn chunk(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn consume(&mut self, cnt: usize) {
+        bytes::Buf::advance(&mut self.buf, cnt);
+    }
+
+    fn split_to(&mut self, cnt: usize) -> BytesMut {
+        self.buf.split_to(cnt)
+    }
+}
@@ -1,9 +1,30 @@
+use bytes::{Bytes, BytesMut};
 use std::{
     io,
     task::{Poll, ready},
 };
 
-use crate::io::AsyncBufRead;
+use crate::{
+    io::AsyncBufRead,
+    slice::{Varint, varint_i64, varint_u64},
+};
+
+macro_rules! poll_get_int {
+    ($($(#[$doc:meta])* $fn:ident: $ty:ty = $from:ident;)*) => {$(
+        $(#[$doc])*
+        #[inline]
+        pub fn $fn(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<$ty>>
+        where
+            B: AsyncBufRead,
+        {
+            const N: usize = size_of::<$ty>();
+            let chunk = ready!(self.poll_get(N, cx)?);
+            let mut bytes = [0u8; N];
+            bytes.copy_from_slice(chunk);
+            Poll::Ready(Ok(<$ty>::$from(bytes)))
+        }
+    )*};
+}
 
 /// Two layer cursor buffer reading.
 #[derive(Debug)]
@@ -20,21 +41,255 @@ impl<B> BufCursor<B> {
     }
 
     /// Try get `len` of bytes, advancing cursor position.
+    ///
+    /// Returns [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error if the underlying io reached
+    /// end of stream before `len` bytes is available.
     pub fn poll_get<'a>(&'a mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<&'a [u8]>>
     where
         B: AsyncBufRead,
     {
+        ready!(self.poll_fill_to(self.read + len, cx)?);
+        let read = self.read;
+        self.read += len;
+        Poll::Ready(Ok(&self.io.chunk()[read..read + len]))
+    }
+
+    /// Try get `len` of bytes, without advancing cursor position.
+    ///
+    /// Returns [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error if the underlying io reached
+    /// end of stream before `len` bytes is available.
+    pub fn poll_peek<'a>(&'a mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<&'a [u8]>>
+    where
+        B: AsyncBufRead,
+    {
+        ready!(self.poll_fill_to(self.read + len, cx)?);
+        Poll::Ready(Ok(&self.io.chunk()[self.read..self.read + len]))
+    }
+
+    /// Try take `len` of bytes as [`Bytes`], advancing cursor position.
+    ///
+    /// Unlike [`poll_get`][BufCursor::poll_get], the returned bytes is not tied to the cursor
+    /// lifetime. If the underlying io is [`BufReader`][crate::io::BufReader], the bytes is split
+    /// from its internal buffer without copying.
+    ///
+    /// Because the bytes is removed from the underlying io buffer, when ready, this also
+    /// [`commit`][BufCursor::commit] the previously read bytes. When pending or error, the cursor
+    /// is not committed.
+    ///
+    /// Returns [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error if the underlying io reached
+    /// end of stream before `len` bytes is available.
+    #[inline]
+    pub fn poll_take(&mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<Bytes>>
+    where
+        B: AsyncBufRead,
+    {
+        self.poll_take_mut(len, cx).map_ok(BytesMut::freeze)
+    }
+
+    /// Try take `len` of bytes as [`BytesMut`], advancing cursor position.
+    ///
+    /// See [`poll_take`][BufCursor::poll_take] for more details.
+    pub fn poll_take_mut(&mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<BytesMut>>
+    where
+        B: AsyncBufRead,
+    {
+        ready!(self.poll_fill_to(self.read + len, cx)?);
+        self.commit();
+        Poll::Ready(Ok(self.io.split_to(len)))
+    }
+
+    /// Try get a byte, advancing cursor position.
+    #[inline]
+    pub fn poll_get_u8(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<u8>>
+    where
+        B: AsyncBufRead,
+    {
+        let chunk = ready!(self.poll_get(1, cx)?);
+        Poll::Ready(Ok(chunk[0]))
+    }
+
+    /// Try get a byte as `i8`, advancing cursor position.
+    #[inline]
+    pub fn poll_get_i8(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<i8>>
+    where
+        B: AsyncBufRead,
+    {
+        let chunk = ready!(self.poll_get(1, cx)?);
+        Poll::Ready(Ok(chunk[0] as i8))
+    }
+
+    poll_get_int! {
+        /// Try get big endian `u16`, advancing cursor position.
+        poll_get_u16_be: u16 = from_be_bytes;
+        /// Try get little endian `u16`, advancing cursor position.
+        poll_get_u16_le: u16 = from_le_bytes;
+        /// Try get big endian `u32`, advancing cursor position.
+        poll_get_u32_be: u32 = from_be_bytes;
+        /// Try get little endian `u32`, advancing cursor position.
+        poll_get_u32_le: u32 = from_le_bytes;
+        /// Try get big endian `u64`, advancing cursor position.
+        poll_get_u64_be: u64 = from_be_bytes;
+        /// Try get little endian `u64`, advancing cursor position.
+        poll_get_u64_le: u64 = from_le_bytes;
+        /// Try get big endian `i16`, advancing cursor position.
+        poll_get_i16_be: i16 = from_be_bytes;
+        /// Try get little endian `i16`, advancing cursor position.
+        poll_get_i16_le: i16 = from_le_bytes;
+        /// Try get big endian `i32`, advancing cursor position.
+        poll_get_i32_be: i32 = from_be_bytes;
+        /// Try get little endian `i32`, advancing cursor position.
+        poll_get_i32_le: i32 = from_le_bytes;
+        /// Try get big endian `i64`, advancing cursor position.
+        poll_get_i64_be: i64 = from_be_bytes;
+        /// Try get little endian `i64`, advancing cursor position.
+        poll_get_i64_le: i64 = from_le_bytes;
+    }
+
+    /// Try get unsigned LEB128 encoded `u64`, advancing cursor position.
+    ///
+    /// Returns [`InvalidData`][io::ErrorKind::InvalidData] error if the value overflows `u64`.
+    #[inline]
+    pub fn poll_get_varint_u64(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<u64>>
+    where
+        B: AsyncBufRead,
+    {
+        self.poll_varint(varint_u64, cx)
+    }
+
+    /// Try get signed LEB128 encoded `i64`, advancing cursor position.
+    ///
+    /// Returns [`InvalidData`][io::ErrorKind::InvalidData] error if the value overflows `i64`.
+    #[inline]
+    pub fn poll_get_varint_i64(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<i64>>
+    where
+        B: AsyncBufRead,
+    {
+        self.poll_varint(varint_i64, cx)
+    }
+
+    /// Try get bytes until `delim` is found, advancing cursor position pass the delimiter.
+    ///
+    /// The returned bytes does not contains the delimiter.
+    ///
+    /// Returns [`InvalidData`][io::ErrorKind::InvalidData] error if the delimiter is not found
+    /// within `max` bytes, and [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error if the
+    /// underlying io reached end of stream before the delimiter is found.
+    #[inline]
+    pub fn poll_until<'a>(
+        &'a mut self,
+        delim: u8,
+        max: usize,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<&'a [u8]>>
+    where
+        B: AsyncBufRead,
+    {
+        self.poll_until_slice(&[delim], max, cx)
+    }
+
+    /// Try get bytes until `delim` sequence is found, advancing cursor position pass the
+    /// delimiter.
+    ///
+    /// The returned bytes does not contains the delimiter.
+    ///
+    /// Returns [`InvalidData`][io::ErrorKind::InvalidData] error if the delimiter is not found
+    /// within `max` bytes, and [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error if the
+    /// underlying io reached end of stream before the delimiter is found.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `delim` is empty.
+    pub fn poll_until_slice<'a>(
+        &'a mut self,
+        delim: &[u8],
+        max: usize,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<&'a [u8]>>
+    where
+        B: AsyncBufRead,
+    {
+        assert!(!delim.is_empty(), "`BufCursor::poll_until_slice` delimiter is empty");
+
+        // the delimiter must start at most at `max`
+        let limit = max.saturating_add(delim.len());
+        let mut searched = 0;
+
         loop {
-            if self.io.chunk().len() >= self.read + len {
+            let chunk = &self.io.chunk()[self.read..];
+            let chunk = &chunk[..chunk.len().min(limit)];
+
+            if let Some(n) = chunk[searched..].windows(delim.len()).position(|e| e == delim) {
                 let read = self.read;
-                self.read += len;
-                return Poll::Ready(Ok(&self.io.chunk()[read..read + len]))
+                let len = searched + n;
+                self.read += len + delim.len();
+                return Poll::Ready(Ok(&self.io.chunk()[read..read + len]));
+            }
+
+            if chunk.len() == limit {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "delimiter not found within max length",
+                )));
             }
 
-            ready!(self.io.poll_read_fill(cx)?);
+            searched = (chunk.len() + 1).saturating_sub(delim.len());
+
+            if ready!(self.io.poll_read_fill(cx)?) == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
         }
     }
 
+    /// Returns `true` if there is no more data after cursor position and the underlying io
+    /// reached end of stream.
+    pub fn poll_is_eof(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<bool>>
+    where
+        B: AsyncBufRead,
+    {
+        if self.io.chunk().len() > self.read {
+            return Poll::Ready(Ok(false));
+        }
+
+        let read = ready!(self.io.poll_read_fill(cx)?);
+        Poll::Ready(Ok(read == 0))
+    }
+
+    /// Returns current cursor position which can be restored later.
+    ///
+    /// The checkpoint is relative to the last commit, it is invalidated by
+    /// [`commit`][BufCursor::commit].
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint { read: self.read }
+    }
+
+    /// Move cursor position to the given checkpoint.
+    ///
+    /// The checkpoint can also be used in a new [`BufCursor`] over the same io, as long as there is
+    /// no commit in between. This allows stateful parser to store the checkpoint, and resume
+    /// parsing from the last complete message instead of the last commit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the checkpoint position is out of the buffered data.
+    #[inline]
+    pub fn restore(&mut self, checkpoint: Checkpoint)
+    where
+        B: AsyncBufRead,
+    {
+        assert!(
+            checkpoint.read <= self.io.chunk().len(),
+            "`BufCursor::restore` checkpoint is out of bounds"
+        );
+        self.read = checkpoint.read;
+    }
+
+    /// Move cursor position back to the last commit.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.read = 0;
+    }
+
     /// Set the underlying io buffer to consume amount of read by cursor, and reset cursor
     /// position.
     #[inline]
@@ -45,6 +300,109 @@ impl<B> BufCursor<B> {
         self.io.consume(self.read);
         self.read = 0;
     }
+
+    /// Same as [`commit`][BufCursor::commit], but returns the consumed data.
+    ///
+    /// If the underlying io is [`BufReader`][crate::io::BufReader], the data is split from its
+    /// internal buffer without copying.
+    #[inline]
+    pub fn commit_split(&mut self) -> BytesMut
+    where
+        B: AsyncBufRead,
+    {
+        self.io.split_to(std::mem::take(&mut self.read))
+    }
+
+    fn poll_varint<T>(
+        &mut self,
+        decode: fn(&[u8]) -> Varint<T>,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<T>>
+    where
+        B: AsyncBufRead,
+    {
+        loop {
+            match decode(&self.io.chunk()[self.read..]) {
+                Varint::Ok(value, len) => {
+                    self.read += len;
+                    return Poll::Ready(Ok(value));
+                }
+                Varint::Overflow => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "varint overflow",
+                    )));
+                }
+                Varint::Incomplete => {}
+            }
+
+            if ready!(self.io.poll_read_fill(cx)?) == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+    }
+
+    fn poll_fill_to(&mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<()>>
+    where
+        B: AsyncBufRead,
+    {
+        while self.io.chunk().len() < len {
+            if ready!(self.io.poll_read_fill(cx)?) == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A position of [`BufCursor`].
+///
+/// Returned from [`BufCursor::checkpoint`].
+///
+/// # Examples
+///
+/// ```
+/// # use std::{io, task::{Poll, ready}};
+/// use tcio::io::{AsyncBufRead, BufCursor, Checkpoint};
+///
+/// /// Parse `\r\n` delimited lines until an empty line.
+/// #[derive(Default)]
+/// struct Parser {
+///     checkpoint: Option<Checkpoint>,
+///     lines: usize,
+/// }
+///
+/// impl Parser {
+///     fn poll_parse<B: AsyncBufRead>(
+///         &mut self,
+///         io: B,
+///         cx: &mut std::task::Context,
+///     ) -> Poll<io::Result<usize>> {
+///         let mut cursor = BufCursor::new(io);
+///
+///         // resume after the last complete line
+///         if let Some(checkpoint) = self.checkpoint {
+///             cursor.restore(checkpoint);
+///         }
+///
+///         loop {
+///             let line = ready!(cursor.poll_until_slice(b"\r\n", 1024, cx)?);
+///             if line.is_empty() {
+///                 break;
+///             }
+///             self.lines += 1;
+///             self.checkpoint = Some(cursor.checkpoint());
+///         }
+///
+///         cursor.commit();
+///         self.checkpoint = None;
+///         Poll::Ready(Ok(std::mem::take(&mut self.lines)))
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    read: usize,
 }
 
 
@@ -61,3 +419,81 @@ impl<B: AsyncBufRead> AsyncBufRead for BufCursor<B> {
         self.read += cnt;
     }
 }
+
+#[test]
+fn test_cursor_lookahead() {
+    use crate::io::Chunks;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let mut io = Chunks::new(&[b"Content-", b"Type: text", b"/html\r", b"\n\r\n"]);
+    let mut cursor = BufCursor::new(&mut io);
+
+    assert!(matches!(cursor.poll_peek(7, &mut cx), Poll::Ready(Ok(b"Content"))));
+    assert!(matches!(cursor.poll_get(7, &mut cx), Poll::Ready(Ok(b"Content"))));
+
+    let checkpoint = cursor.checkpoint();
+
+    assert!(matches!(cursor.poll_until(b':', 16, &mut cx), Poll::Ready(Ok(b"-Type"))));
+    let value = cursor.poll_until_slice(b"\r\n", 16, &mut cx);
+    assert!(matches!(value, Poll::Ready(Ok(b" text/html"))));
+
+    cursor.restore(checkpoint);
+    assert!(matches!(cursor.poll_get(1, &mut cx), Poll::Ready(Ok(b"-"))));
+
+    // delimiter not found within max length
+    let err = cursor.poll_until_slice(b"\r\n", 8, &mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::InvalidData));
+
+    cursor.reset();
+    assert!(matches!(cursor.poll_until_slice(b"\r\n\r\n", 32, &mut cx), Poll::Ready(Ok(_))));
+    cursor.commit();
+    assert!(matches!(cursor.poll_is_eof(&mut cx), Poll::Ready(Ok(true))));
+
+    let err = cursor.poll_until(b'\n', 16, &mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn test_cursor_get_int() {
+    use crate::io::Chunks;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let mut io = Chunks::new(&[b"\xff\x00", b"\x01\x02\x00\x00", b"\x00\xe5\x8e", b"\x26"]);
+    let mut cursor = BufCursor::new(&mut io);
+
+    assert!(matches!(cursor.poll_get_i8(&mut cx), Poll::Ready(Ok(-1))));
+    assert!(matches!(cursor.poll_get_u16_be(&mut cx), Poll::Ready(Ok(0x0001))));
+    assert!(matches!(cursor.poll_get_u32_le(&mut cx), Poll::Ready(Ok(0x0002))));
+    assert!(matches!(cursor.poll_get_varint_u64(&mut cx), Poll::Ready(Ok(624485))));
+
+    let err = cursor.poll_get_u16_le(&mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn test_cursor_take() {
+    use crate::io::Chunks;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let mut io = Chunks::new(&[b"\x00\x03Fo", b"", b"o\x00\x03Bar"]);
+
+    let mut cursor = BufCursor::new(&mut io);
+    assert!(matches!(cursor.poll_get_u16_be(&mut cx), Poll::Ready(Ok(3))));
+    assert!(cursor.poll_take(3, &mut cx).is_pending());
+
+    // pending does not commit
+    assert_eq!(io.chunk(), b"\x00\x03Fo");
+    let mut cursor = BufCursor::new(&mut io);
+    assert!(matches!(cursor.poll_get_u16_be(&mut cx), Poll::Ready(Ok(3))));
+    let Poll::Ready(Ok(foo)) = cursor.poll_take(3, &mut cx) else {
+        panic!("expected ready")
+    };
+    assert!(matches!(cursor.poll_get_u16_be(&mut cx), Poll::Ready(Ok(3))));
+    let Poll::Ready(Ok(bar)) = cursor.poll_take(3, &mut cx) else {
+        panic!("expected ready")
+    };
+
+    assert_eq!(foo, "Foo");
+    assert_eq!(bar, "Bar");
+    assert!(io.chunk().is_empty());
+}
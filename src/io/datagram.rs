@@ -0,0 +1,239 @@
+use bytes::{BufMut, BytesMut};
+use std::{
+    io,
+    task::{Poll, ready},
+};
+
+/// Asynchronous datagram receive operation.
+pub trait AsyncDatagramRecv {
+    /// Address of the datagram sender.
+    type Addr;
+
+    /// Polls for receive readiness.
+    fn poll_recv_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>>;
+
+    /// Tries to receive a single datagram into the spare capacity of the buffer, returning how
+    /// many bytes were received and the sender address.
+    ///
+    /// If the spare capacity is smaller than the datagram, the excess bytes are discarded.
+    fn try_recv_from(&self, buf: &mut BytesMut) -> io::Result<(usize, Self::Addr)>;
+
+    /// Tries to receive a single datagram into the spare capacity of the buffer, returning how
+    /// many bytes were received and the sender address.
+    ///
+    /// Returns [`Poll::Pending`] if the underlying socket not ready for receiving.
+    fn poll_recv_from(
+        &self,
+        buf: &mut BytesMut,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<(usize, Self::Addr)>> {
+        match self.try_recv_from(buf) {
+            Ok(ok) => Poll::Ready(Ok(ok)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                tri!(ready!(self.poll_recv_ready(cx)));
+                self.poll_recv_from(buf, cx)
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    /// Receive a single datagram into the spare capacity of the buffer, returning how many bytes
+    /// were received and the sender address.
+    #[inline]
+    fn recv_from(
+        &self,
+        buf: &mut BytesMut,
+    ) -> impl Future<Output = io::Result<(usize, Self::Addr)>> {
+        std::future::poll_fn(|cx| self.poll_recv_from(buf, cx))
+    }
+}
+
+/// Asynchronous datagram send operation.
+pub trait AsyncDatagramSend {
+    /// Address of the datagram target.
+    type Addr;
+
+    /// Polls for send readiness.
+    fn poll_send_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>>;
+
+    /// Tries to send a single datagram to the target, returning how many bytes were sent.
+    fn try_send_to(&self, buf: &[u8], target: &Self::Addr) -> io::Result<usize>;
+
+    /// Tries to send a single datagram to the target, returning how many bytes were sent.
+    ///
+    /// Returns [`Poll::Pending`] if the underlying socket not ready for sending.
+    fn poll_send_to(
+        &self,
+        buf: &[u8],
+        target: &Self::Addr,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<usize>> {
+        match self.try_send_to(buf, target) {
+            Ok(sent) => Poll::Ready(Ok(sent)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                tri!(ready!(self.poll_send_ready(cx)));
+                self.poll_send_to(buf, target, cx)
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    /// Send a single datagram to the target, returning how many bytes were sent.
+    #[inline]
+    fn send_to(
+        &self,
+        buf: &[u8],
+        target: &Self::Addr,
+    ) -> impl Future<Output = io::Result<usize>> {
+        std::future::poll_fn(|cx| self.poll_send_to(buf, target, cx))
+    }
+}
+
+// ===== Macros =====
+
+macro_rules! tri {
+    ($e:expr) => {
+        match $e {
+            Ok(ok) => ok,
+            Err(err) => return Poll::Ready(Err(err)),
+        }
+    };
+}
+
+use tri;
+
+/// Receive into the spare capacity of the buffer, and advance the buffer.
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+fn recv_buf<A>(
+    buf: &mut BytesMut,
+    recv: impl FnOnce(&mut [u8]) -> io::Result<(usize, A)>,
+) -> io::Result<(usize, A)> {
+    let (read, addr) = {
+        // SAFETY: we will only write initialized value and `MaybeUninit<T>` is guaranteed to
+        // have the same size as `T`:
+        let dst = unsafe {
+            &mut *(buf.chunk_mut().as_uninit_slice_mut() as *mut [std::mem::MaybeUninit<u8>]
+                as *mut [u8])
+        };
+        recv(dst)?
+    };
+
+    // SAFETY: This is guaranteed to be the number of initialized by `recv`
+    unsafe {
+        buf.advance_mut(read);
+    }
+
+    Ok((read, addr))
+}
+
+// ===== tokio =====
+
+#[cfg(feature = "tokio")]
+mod tokio_io {
+    use super::*;
+
+    use std::net::SocketAddr;
+    use tokio::net::UdpSocket;
+
+    impl AsyncDatagramRecv for UdpSocket {
+        type Addr = SocketAddr;
+
+        #[inline]
+        fn poll_recv_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+            self.poll_recv_ready(cx)
+        }
+
+        #[inline]
+        fn try_recv_from(&self, buf: &mut BytesMut) -> io::Result<(usize, Self::Addr)> {
+            recv_buf(buf, |dst| self.try_recv_from(dst))
+        }
+    }
+
+    impl AsyncDatagramSend for UdpSocket {
+        type Addr = SocketAddr;
+
+        #[inline]
+        fn poll_send_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+            self.poll_send_ready(cx)
+        }
+
+        #[inline]
+        fn try_send_to(&self, buf: &[u8], target: &Self::Addr) -> io::Result<usize> {
+            self.try_send_to(buf, *target)
+        }
+    }
+
+    #[cfg(unix)]
+    mod unix {
+        use super::*;
+        use tokio::net::{UnixDatagram, unix::SocketAddr};
+
+        impl AsyncDatagramRecv for UnixDatagram {
+            type Addr = SocketAddr;
+
+            #[inline]
+            fn poll_recv_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                self.poll_recv_ready(cx)
+            }
+
+            #[inline]
+            fn try_recv_from(&self, buf: &mut BytesMut) -> io::Result<(usize, Self::Addr)> {
+                recv_buf(buf, |dst| self.try_recv_from(dst))
+            }
+        }
+
+        /// Sending to unnamed address returns [`InvalidInput`][io::ErrorKind::InvalidInput]
+        /// error.
+        impl AsyncDatagramSend for UnixDatagram {
+            type Addr = SocketAddr;
+
+            #[inline]
+            fn poll_send_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                self.poll_send_ready(cx)
+            }
+
+            #[inline]
+            fn try_send_to(&self, buf: &[u8], target: &Self::Addr) -> io::Result<usize> {
+                match target.as_pathname() {
+                    Some(path) => self.try_send_to(buf, path),
+                    None => Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "cannot send to unnamed unix socket address",
+                    )),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_udp_socket() {
+    use tokio::net::UdpSocket;
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+
+    rt.block_on(async {
+        let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let b_addr = b.local_addr().unwrap();
+
+        assert_eq!(AsyncDatagramSend::send_to(&a, b"Foo", &b_addr).await.unwrap(), 3);
+        assert_eq!(AsyncDatagramSend::send_to(&a, b"BarBaz", &b_addr).await.unwrap(), 6);
+
+        let mut buf = BytesMut::with_capacity(64);
+        let (len, addr) = AsyncDatagramRecv::recv_from(&b, &mut buf).await.unwrap();
+        assert_eq!(len, 3);
+        assert_eq!(addr, a.local_addr().unwrap());
+        assert_eq!(&buf[..], b"Foo");
+
+        // excess bytes are discarded
+        let mut buf = BytesMut::with_capacity(3);
+        let (len, _) = AsyncDatagramRecv::recv_from(&b, &mut buf).await.unwrap();
+        assert_eq!(len, 3);
+        assert_eq!(&buf[..], b"Bar");
+    });
+}
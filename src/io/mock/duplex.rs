@@ -0,0 +1,201 @@
+use bytes::{Buf, BytesMut};
+use std::{
+    io,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Poll, Waker},
+};
+
+use crate::io::{AsyncIoRead, AsyncIoWrite};
+
+/// Creates a pair of connected in-memory io.
+///
+/// Data written to one end can be read from the other end. Each direction buffers at most
+/// `max_buf_size` bytes, writing to a full buffer returns [`Poll::Pending`] until the other end
+/// reads.
+///
+/// Dropping one end will make reading from the other end returns end of stream, and writing
+/// returns [`BrokenPipe`][io::ErrorKind::BrokenPipe] error. Shutting down one end only closes
+/// one direction.
+///
+/// # Panics
+///
+/// Panics if `max_buf_size` is `0`.
+pub fn duplex(max_buf_size: usize) -> (Duplex, Duplex) {
+    assert!(max_buf_size > 0, "duplex buffer size cannot be zero");
+
+    let one = Arc::new(Mutex::new(Pipe::new(max_buf_size)));
+    let two = Arc::new(Mutex::new(Pipe::new(max_buf_size)));
+    (
+        Duplex { read: one.clone(), write: two.clone() },
+        Duplex { read: two, write: one },
+    )
+}
+
+/// In-memory io created with [`duplex`].
+#[derive(Debug)]
+pub struct Duplex {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+}
+
+#[derive(Debug)]
+struct Pipe {
+    buf: BytesMut,
+    max_buf_size: usize,
+    closed: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn new(max_buf_size: usize) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            max_buf_size,
+            closed: false,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.max_buf_size - self.buf.len()
+    }
+}
+
+fn lock(pipe: &Mutex<Pipe>) -> MutexGuard<'_, Pipe> {
+    pipe.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl AsyncIoRead for Duplex {
+    fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let mut pipe = lock(&self.read);
+        if !pipe.buf.is_empty() || pipe.closed {
+            return Poll::Ready(Ok(()));
+        }
+        pipe.read_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut pipe = lock(&self.read);
+        if pipe.buf.is_empty() {
+            return match pipe.closed {
+                true => Ok(0),
+                false => Err(io::ErrorKind::WouldBlock.into()),
+            };
+        }
+
+        let len = buf.len().min(pipe.buf.len());
+        buf[..len].copy_from_slice(&pipe.buf[..len]);
+        pipe.buf.advance(len);
+
+        if let Some(waker) = pipe.write_waker.take() {
+            waker.wake();
+        }
+        Ok(len)
+    }
+
+    fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.try_read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl AsyncIoWrite for Duplex {
+    fn poll_write_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let mut pipe = lock(&self.write);
+        if pipe.remaining() != 0 || pipe.closed {
+            return Poll::Ready(Ok(()));
+        }
+        pipe.write_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.try_write_vectored(&[io::IoSlice::new(buf)])
+    }
+
+    fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let mut pipe = lock(&self.write);
+        if pipe.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if bufs.iter().all(|b| b.is_empty()) {
+            return Ok(0);
+        }
+        if pipe.remaining() == 0 {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        let mut written = 0;
+        for buf in bufs {
+            let len = buf.len().min(pipe.remaining());
+            pipe.buf.extend_from_slice(&buf[..len]);
+            written += len;
+        }
+
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    /// Close the write side, the other end will read end of stream.
+    fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+        lock(&self.write).close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for Duplex {
+    fn drop(&mut self) {
+        lock(&self.read).close();
+        lock(&self.write).close();
+    }
+}
+
+#[test]
+fn test_duplex() {
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let (a, b) = duplex(4);
+
+    assert!(a.poll_read(&mut [0; 4], &mut cx).is_pending());
+
+    assert!(matches!(b.poll_write(b"FooBar", &mut cx), Poll::Ready(Ok(4))));
+    assert!(b.poll_write(b"Bar", &mut cx).is_pending());
+
+    let mut buf = [0; 8];
+    assert!(matches!(a.poll_read(&mut buf, &mut cx), Poll::Ready(Ok(4))));
+    assert_eq!(&buf[..4], b"FooB");
+    assert!(matches!(b.poll_write(b"ar", &mut cx), Poll::Ready(Ok(2))));
+
+    // half close
+    assert!(b.poll_shutdown(&mut cx).is_ready());
+    assert!(matches!(a.poll_read(&mut buf, &mut cx), Poll::Ready(Ok(2))));
+    assert!(matches!(a.poll_read(&mut buf, &mut cx), Poll::Ready(Ok(0))));
+    assert!(matches!(a.poll_write(b"Foo", &mut cx), Poll::Ready(Ok(3))));
+    assert!(matches!(b.poll_read(&mut buf, &mut cx), Poll::Ready(Ok(3))));
+
+    drop(b);
+    assert!(matches!(a.poll_read(&mut buf, &mut cx), Poll::Ready(Ok(0))));
+    let err = a.poll_write(b"Foo", &mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::BrokenPipe));
+}
@@ -0,0 +1,484 @@
+//! In-memory io for testing.
+//!
+//! [`Mock`] is a scripted io object, each read and write is checked against a sequence of
+//! actions built with [`Builder`]. [`duplex`] creates a pair of connected in-memory io objects.
+//!
+//! # Examples
+//!
+//! ```
+//! use tcio::io::{AsyncBufRead, BufReader, mock::Builder};
+//!
+//! let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+//! let mut io = BufReader::new(Builder::new().read(b"Foo").would_block().read(b"Bar").build());
+//!
+//! assert!(io.poll_read_fill(&mut cx).is_ready());
+//! assert!(io.poll_read_fill(&mut cx).is_pending());
+//! assert!(io.poll_read_fill(&mut cx).is_ready());
+//! assert_eq!(io.chunk(), b"FooBar");
+//! ```
+use bytes::{Buf, Bytes};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Poll, Waker},
+};
+
+use crate::{
+    fmt::lossy,
+    io::{AsyncIoRead, AsyncIoWrite},
+};
+
+mod duplex;
+
+pub use duplex::{Duplex, duplex};
+
+/// Builder for [`Mock`].
+///
+/// Actions are executed in the order they are added. A read will not continue until all
+/// previous write actions is completed, and vice versa.
+#[derive(Debug, Default)]
+pub struct Builder {
+    actions: VecDeque<Action>,
+    write_limit: Option<usize>,
+}
+
+#[derive(Debug)]
+enum Action {
+    Read(Bytes),
+    ReadError(io::Error),
+    Write(Bytes),
+    WriteError(io::Error),
+    Shutdown,
+    WouldBlock,
+}
+
+impl Builder {
+    /// Creates new [`Builder`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sequence a read operation that returns `buf`.
+    ///
+    /// If the read buffer is smaller than `buf`, the remaining data is returned by the next read.
+    pub fn read(&mut self, buf: &[u8]) -> &mut Self {
+        self.actions.push_back(Action::Read(Bytes::copy_from_slice(buf)));
+        self
+    }
+
+    /// Sequence a read operation that returns an error.
+    pub fn read_error(&mut self, error: io::Error) -> &mut Self {
+        self.actions.push_back(Action::ReadError(error));
+        self
+    }
+
+    /// Sequence a write operation that expects `buf` to be written.
+    ///
+    /// Writing data that does not match `buf` will panic.
+    pub fn write(&mut self, buf: &[u8]) -> &mut Self {
+        self.actions.push_back(Action::Write(Bytes::copy_from_slice(buf)));
+        self
+    }
+
+    /// Sequence a write operation that returns an error.
+    pub fn write_error(&mut self, error: io::Error) -> &mut Self {
+        self.actions.push_back(Action::WriteError(error));
+        self
+    }
+
+    /// Sequence a write side shutdown.
+    pub fn shutdown(&mut self) -> &mut Self {
+        self.actions.push_back(Action::Shutdown);
+        self
+    }
+
+    /// Sequence the next read or write operation to returns
+    /// [`WouldBlock`][io::ErrorKind::WouldBlock].
+    ///
+    /// The following readiness poll returns [`Poll::Pending`] once, and wakes the task
+    /// immediately.
+    pub fn would_block(&mut self) -> &mut Self {
+        self.actions.push_back(Action::WouldBlock);
+        self
+    }
+
+    /// Set the maximum bytes written for each write operation, to simulate short writes.
+    pub fn write_limit(&mut self, limit: usize) -> &mut Self {
+        self.write_limit = Some(limit);
+        self
+    }
+
+    /// Build a [`Mock`] with the sequenced actions.
+    ///
+    /// When all actions is completed, read returns end of stream and write panics.
+    pub fn build(&mut self) -> Mock {
+        self.build_inner(false)
+    }
+
+    /// Build a [`Mock`] with a [`Handle`] to sequence more actions.
+    ///
+    /// When all actions is completed, read and write is pending until more actions is sequenced
+    /// or the [`Handle`] is dropped.
+    pub fn build_with_handle(&mut self) -> (Mock, Handle) {
+        let mock = self.build_inner(true);
+        let handle = Handle { shared: mock.shared.clone() };
+        (mock, handle)
+    }
+
+    fn build_inner(&mut self, has_handle: bool) -> Mock {
+        let state = State {
+            actions: std::mem::take(&mut self.actions),
+            write_limit: self.write_limit.unwrap_or(usize::MAX),
+            has_handle,
+            read_blocked: false,
+            write_blocked: false,
+            read_waker: None,
+            write_waker: None,
+        };
+        Mock { shared: Arc::new(Mutex::new(state)) }
+    }
+}
+
+/// Scripted in-memory io.
+///
+/// Created with [`Builder`], see [module level docs][self] for more details.
+///
+/// # Panics
+///
+/// Dropping [`Mock`] with remaining actions will panic.
+#[derive(Debug)]
+pub struct Mock {
+    shared: Arc<Mutex<State>>,
+}
+
+/// Handle to sequence more actions to a [`Mock`].
+///
+/// Created with [`Builder::build_with_handle`].
+#[derive(Debug)]
+pub struct Handle {
+    shared: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    actions: VecDeque<Action>,
+    write_limit: usize,
+    has_handle: bool,
+    read_blocked: bool,
+    write_blocked: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+fn lock(shared: &Mutex<State>) -> MutexGuard<'_, State> {
+    shared.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl State {
+    fn wake(&mut self) {
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn push(&mut self, action: Action) {
+        self.actions.push_back(action);
+        self.wake();
+    }
+
+    fn pop(&mut self) -> Option<Action> {
+        let action = self.actions.pop_front();
+        self.wake();
+        action
+    }
+
+    fn is_read_ready(&self) -> bool {
+        match self.actions.front() {
+            Some(Action::Read(_) | Action::ReadError(_) | Action::WouldBlock) => true,
+            Some(Action::Write(_) | Action::WriteError(_) | Action::Shutdown) => false,
+            None => !self.has_handle,
+        }
+    }
+
+    fn is_write_ready(&self) -> bool {
+        match self.actions.front() {
+            Some(Action::Write(_) | Action::WriteError(_) | Action::Shutdown | Action::WouldBlock) => {
+                true
+            }
+            Some(Action::Read(_) | Action::ReadError(_)) => false,
+            None => !self.has_handle,
+        }
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.actions.front_mut() {
+            Some(Action::Read(data)) => {
+                let len = buf.len().min(data.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                data.advance(len);
+                if data.is_empty() {
+                    self.pop();
+                }
+                Ok(len)
+            }
+            Some(Action::ReadError(_)) => match self.pop() {
+                Some(Action::ReadError(err)) => Err(err),
+                _ => unreachable!(),
+            },
+            Some(Action::WouldBlock) => {
+                self.pop();
+                self.read_blocked = true;
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            Some(Action::Write(_) | Action::WriteError(_) | Action::Shutdown) => {
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            None if self.has_handle => Err(io::ErrorKind::WouldBlock.into()),
+            None => Ok(0),
+        }
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.actions.front_mut() {
+            Some(Action::Write(expected)) => {
+                let len = buf.len().min(expected.len()).min(self.write_limit);
+                assert!(
+                    buf[..len] == expected[..len],
+                    "mismatched write, expected {:?}, found {:?}",
+                    lossy(expected),
+                    lossy(&buf),
+                );
+                expected.advance(len);
+                if expected.is_empty() {
+                    self.pop();
+                }
+                Ok(len)
+            }
+            Some(Action::WriteError(_)) => match self.pop() {
+                Some(Action::WriteError(err)) => Err(err),
+                _ => unreachable!(),
+            },
+            Some(Action::WouldBlock) => {
+                self.pop();
+                self.write_blocked = true;
+                Err(io::ErrorKind::WouldBlock.into())
+            }
+            Some(Action::Shutdown) => panic!("unexpected write {:?}, expected shutdown", lossy(&buf)),
+            Some(Action::Read(_) | Action::ReadError(_)) => Err(io::ErrorKind::WouldBlock.into()),
+            None if self.has_handle => Err(io::ErrorKind::WouldBlock.into()),
+            None => panic!("unexpected write {:?}", lossy(&buf)),
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        match self.actions.front() {
+            Some(Action::Shutdown) => {
+                self.pop();
+                Poll::Ready(Ok(()))
+            }
+            Some(Action::WouldBlock) => {
+                self.pop();
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Some(Action::Write(_) | Action::WriteError(_)) => panic!("unexpected shutdown"),
+            Some(Action::Read(_) | Action::ReadError(_)) => {
+                self.write_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            None if self.has_handle => {
+                self.write_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            None => panic!("unexpected shutdown"),
+        }
+    }
+}
+
+impl AsyncIoRead for Mock {
+    fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let mut state = lock(&self.shared);
+        if std::mem::take(&mut state.read_blocked) {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        if state.is_read_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        state.read_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        lock(&self.shared).try_read(buf)
+    }
+
+    fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.try_read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl AsyncIoWrite for Mock {
+    fn poll_write_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let mut state = lock(&self.shared);
+        if std::mem::take(&mut state.write_blocked) {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        if state.is_write_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        state.write_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+        lock(&self.shared).try_write(buf)
+    }
+
+    fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let mut state = lock(&self.shared);
+        let mut written = 0;
+        for buf in bufs.iter().filter(|b| !b.is_empty()) {
+            // write limit applies to the whole vectored write
+            let limit = state.write_limit - written;
+            if limit == 0 {
+                break;
+            }
+            let buf = &buf[..buf.len().min(limit)];
+            let result = state.try_write(buf);
+            match result {
+                Ok(len) => {
+                    written += len;
+                    if len < buf.len() {
+                        break;
+                    }
+                }
+                Err(_) if written != 0 => break,
+                Err(err) => return Err(err),
+            }
+            if !matches!(state.actions.front(), Some(Action::Write(_))) {
+                break;
+            }
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_shutdown(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        lock(&self.shared).poll_shutdown(cx)
+    }
+}
+
+impl Drop for Mock {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        let state = lock(&self.shared);
+        if let Some(action) = state.actions.front() {
+            panic!("mock io dropped with remaining actions, next action: {action:?}");
+        }
+    }
+}
+
+impl Handle {
+    /// Sequence a read operation that returns `buf`.
+    pub fn read(&mut self, buf: &[u8]) -> &mut Self {
+        lock(&self.shared).push(Action::Read(Bytes::copy_from_slice(buf)));
+        self
+    }
+
+    /// Sequence a read operation that returns an error.
+    pub fn read_error(&mut self, error: io::Error) -> &mut Self {
+        lock(&self.shared).push(Action::ReadError(error));
+        self
+    }
+
+    /// Sequence a write operation that expects `buf` to be written.
+    pub fn write(&mut self, buf: &[u8]) -> &mut Self {
+        lock(&self.shared).push(Action::Write(Bytes::copy_from_slice(buf)));
+        self
+    }
+
+    /// Sequence a write operation that returns an error.
+    pub fn write_error(&mut self, error: io::Error) -> &mut Self {
+        lock(&self.shared).push(Action::WriteError(error));
+        self
+    }
+
+    /// Sequence a write side shutdown.
+    pub fn shutdown(&mut self) -> &mut Self {
+        lock(&self.shared).push(Action::Shutdown);
+        self
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        let mut state = lock(&self.shared);
+        state.has_handle = false;
+        state.wake();
+    }
+}
+
+#[test]
+fn test_mock() {
+    use crate::io::{AsyncBufRead, BufCursor, BufReader};
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+
+    // partial read resumption
+    let mut io = BufReader::new(
+        Builder::new()
+            .read(b"Content-")
+            .would_block()
+            .read(b"Type\nFoo")
+            .write(b"Bar")
+            .build(),
+    );
+    let mut cursor = BufCursor::new(&mut io);
+    assert!(cursor.poll_until(b'\n', 64, &mut cx).is_pending());
+    let line = cursor.poll_until(b'\n', 64, &mut cx);
+    assert!(matches!(line, Poll::Ready(Ok(b"Content-Type"))));
+    cursor.commit();
+
+    // read is pending until write completes
+    assert_eq!(io.chunk(), b"Foo");
+    assert!(io.poll_read_fill(&mut cx).is_pending());
+
+    // short writes
+    let io = io.into_parts().0;
+    assert!(matches!(io.poll_write(b"Bar", &mut cx), Poll::Ready(Ok(3))));
+
+    let io = Builder::new().write(b"FooBar").write_limit(2).build();
+    assert!(matches!(io.poll_write(b"FooBar", &mut cx), Poll::Ready(Ok(2))));
+    assert!(matches!(io.poll_write_all_buf(&mut &b"oBar"[..], &mut cx), Poll::Ready(Ok(()))));
+
+    let io = Builder::new().read_error(io::ErrorKind::ConnectionReset.into()).build();
+    let err = io.poll_read(&mut [0; 4], &mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::ConnectionReset));
+
+    // handle
+    let (io, mut handle) = Builder::new().build_with_handle();
+    assert!(io.poll_read(&mut [0; 4], &mut cx).is_pending());
+    handle.read(b"Foo");
+    assert!(matches!(io.poll_read(&mut [0; 4], &mut cx), Poll::Ready(Ok(3))));
+    drop(handle);
+    assert!(matches!(io.poll_read(&mut [0; 4], &mut cx), Poll::Ready(Ok(0))));
+}
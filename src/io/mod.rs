@@ -1,11 +1,21 @@
 //! Asynchronous io.
 mod read;
 mod write;
+mod datagram;
 mod bufread;
+mod bufwrite;
 mod cursor;
 
+pub mod mock;
+
+#[cfg(test)]
+mod chunks;
+#[cfg(test)]
+pub(crate) use chunks::Chunks;
+
 pub use read::{AsyncIoRead, poll_read_fn};
 pub use write::AsyncIoWrite;
+pub use datagram::{AsyncDatagramRecv, AsyncDatagramSend};
 pub use bufread::{AsyncBufRead, BufReader};
-pub use cursor::BufCursor;
-
+pub use bufwrite::{AsyncBufWrite, BufWriter};
+pub use cursor::{BufCursor, Checkpoint};
@@ -139,7 +139,7 @@ use tri;
 mod tokio_io {
     use super::*;
 
-    use tokio::net::TcpStream;
+    use tokio::net::{TcpStream, UdpSocket, tcp};
 
     impl AsyncIoRead for TcpStream {
         #[inline]
@@ -158,10 +158,72 @@ mod tokio_io {
         }
     }
 
+    /// Split halves delegate to the underlying stream.
+    macro_rules! impl_read_half {
+        ($stream:ty => $($half:ty),*) => {$(
+            impl AsyncIoRead for $half {
+                #[inline]
+                fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                    AsRef::<$stream>::as_ref(self).poll_read_ready(cx)
+                }
+
+                #[inline]
+                fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+                    AsRef::<$stream>::as_ref(self).try_read(buf)
+                }
+
+                #[inline]
+                fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+                    AsRef::<$stream>::as_ref(self).try_read_vectored(bufs)
+                }
+            }
+        )*};
+    }
+
+    impl_read_half!(TcpStream => tcp::OwnedReadHalf, tcp::ReadHalf<'_>);
+
+    /// Datagram socket read a single datagram for each read.
+    ///
+    /// If the buffer is smaller than the datagram, the excess bytes are discarded.
+    ///
+    /// Zero-length datagram is read as `Ok(0)`, which is indistinguishable from end of
+    /// stream, use [`AsyncDatagramRecv`][crate::io::AsyncDatagramRecv] to receive zero-length
+    /// datagram.
+    impl AsyncIoRead for UdpSocket {
+        #[inline]
+        fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+            self.poll_recv_ready(cx)
+        }
+
+        #[inline]
+        fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+            self.try_recv(buf)
+        }
+
+        /// Datagram is read into the first non-empty buffer, returns `Ok(0)` without receiving
+        /// if all buffers are empty.
+        #[inline]
+        fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+            match bufs.iter_mut().find(|b| !b.is_empty()) {
+                Some(buf) => self.try_recv(buf),
+                None => Ok(0),
+            }
+        }
+    }
+
     #[cfg(unix)]
     mod unix {
         use super::*;
-        use tokio::net::UnixStream;
+        use std::{
+            fs::File,
+            io::Read,
+            mem::ManuallyDrop,
+            os::fd::{AsRawFd, FromRawFd},
+        };
+        use tokio::{
+            io::{Interest, unix::AsyncFd},
+            net::{UnixDatagram, UnixStream, unix},
+        };
 
         impl AsyncIoRead for UnixStream {
             #[inline]
@@ -179,6 +241,69 @@ mod tokio_io {
                 self.try_read_vectored(bufs)
             }
         }
+
+        impl_read_half!(UnixStream => unix::OwnedReadHalf, unix::ReadHalf<'_>);
+
+        /// Datagram socket read a single datagram for each read.
+        ///
+        /// If the buffer is smaller than the datagram, the excess bytes are discarded.
+        ///
+        /// Zero-length datagram is read as `Ok(0)`, which is indistinguishable from end of
+        /// stream, use [`AsyncDatagramRecv`][crate::io::AsyncDatagramRecv] to receive zero-length
+        /// datagram.
+        impl AsyncIoRead for UnixDatagram {
+            #[inline]
+            fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                self.poll_recv_ready(cx)
+            }
+
+            #[inline]
+            fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+                self.try_recv(buf)
+            }
+
+            /// Datagram is read into the first non-empty buffer, returns `Ok(0)` without receiving
+            /// if all buffers are empty.
+            #[inline]
+            fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+                match bufs.iter_mut().find(|b| !b.is_empty()) {
+                    Some(buf) => self.try_recv(buf),
+                    None => Ok(0),
+                }
+            }
+        }
+
+        /// The file descriptor must be in non-blocking mode.
+        impl<T: AsRawFd> AsyncIoRead for AsyncFd<T> {
+            #[inline]
+            fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                // the guard is dropped without clearing readiness, readiness is cleared by
+                // `try_io` when the read returns `WouldBlock`
+                self.poll_read_ready(cx).map_ok(drop)
+            }
+
+            #[inline]
+            fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+                self.try_io(Interest::READABLE, |fd| fd_file(fd).read(buf))
+            }
+
+            #[inline]
+            fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+                self.try_io(Interest::READABLE, |fd| fd_file(fd).read_vectored(bufs))
+            }
+        }
+
+        /// Borrow a file descriptor as [`File`] without taking ownership.
+        pub(crate) fn fd_file<T: AsRawFd>(fd: &T) -> ManuallyDrop<File> {
+            // SAFETY: the file descriptor is valid for the lifetime of `fd`, and `ManuallyDrop`
+            // prevents the file descriptor from being closed
+            ManuallyDrop::new(unsafe { File::from_raw_fd(fd.as_raw_fd()) })
+        }
     }
+
+    #[cfg(unix)]
+    pub(crate) use unix::fd_file;
 }
 
+#[cfg(all(feature = "tokio", unix))]
+pub(crate) use tokio_io::fd_file;
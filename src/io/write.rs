@@ -91,6 +91,14 @@ pub trait AsyncIoWrite {
 
         Poll::Ready(Ok(()))
     }
+
+    /// Shuts down the write side of the stream.
+    ///
+    /// After shutdown, the peer will observe end of stream once all written data is received.
+    ///
+    /// Io without write side shutdown, such as datagram socket, returns `Ok` immediately and
+    /// should document it.
+    fn poll_shutdown(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>>;
 }
 
 // ===== Macros =====
@@ -112,7 +120,30 @@ use tri;
 mod tokio_io {
     use super::*;
 
-    use tokio::{io::AsyncWrite, net::TcpStream};
+    use std::{mem::ManuallyDrop, net::Shutdown};
+    use tokio::{
+        io::AsyncWrite,
+        net::{TcpStream, UdpSocket, tcp},
+    };
+
+    /// Shutdown the write side of tcp stream with shared reference.
+    fn tcp_shutdown(io: &TcpStream) -> io::Result<()> {
+        #[cfg(unix)]
+        let stream = {
+            use std::os::fd::{AsRawFd, FromRawFd};
+            // SAFETY: the file descriptor is valid for the lifetime of `io`, and `ManuallyDrop`
+            // prevents the file descriptor from being closed
+            ManuallyDrop::new(unsafe { std::net::TcpStream::from_raw_fd(io.as_raw_fd()) })
+        };
+        #[cfg(windows)]
+        let stream = {
+            use std::os::windows::io::{AsRawSocket, FromRawSocket};
+            // SAFETY: the socket is valid for the lifetime of `io`, and `ManuallyDrop` prevents
+            // the socket from being closed
+            ManuallyDrop::new(unsafe { std::net::TcpStream::from_raw_socket(io.as_raw_socket()) })
+        };
+        stream.shutdown(Shutdown::Write)
+    }
 
     impl AsyncIoWrite for TcpStream {
         #[inline]
@@ -134,12 +165,97 @@ mod tokio_io {
         fn is_write_vectored(&self) -> bool {
             AsyncWrite::is_write_vectored(self)
         }
+
+        #[inline]
+        fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+            Poll::Ready(tcp_shutdown(self))
+        }
+    }
+
+    /// Split halves delegate to the underlying stream.
+    macro_rules! impl_write_half {
+        ($stream:ty, $shutdown:expr => $($half:ty),*) => {$(
+            impl AsyncIoWrite for $half {
+                #[inline]
+                fn poll_write_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                    AsRef::<$stream>::as_ref(self).poll_write_ready(cx)
+                }
+
+                #[inline]
+                fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+                    AsRef::<$stream>::as_ref(self).try_write(buf)
+                }
+
+                #[inline]
+                fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+                    AsRef::<$stream>::as_ref(self).try_write_vectored(bufs)
+                }
+
+                #[inline]
+                fn is_write_vectored(&self) -> bool {
+                    AsyncWrite::is_write_vectored(AsRef::<$stream>::as_ref(self))
+                }
+
+                #[inline]
+                fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+                    Poll::Ready($shutdown(AsRef::<$stream>::as_ref(self)))
+                }
+            }
+        )*};
+    }
+
+    impl_write_half!(TcpStream, tcp_shutdown => tcp::OwnedWriteHalf, tcp::WriteHalf<'_>);
+
+    /// Datagram socket send a single datagram for each write.
+    impl AsyncIoWrite for UdpSocket {
+        #[inline]
+        fn poll_write_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+            self.poll_send_ready(cx)
+        }
+
+        #[inline]
+        fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+            self.try_send(buf)
+        }
+
+        /// Only the first non-empty buffer is sent as a datagram.
+        #[inline]
+        fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+            let buf = bufs.iter().find(|b| !b.is_empty()).map_or(&[][..], |b| &**b);
+            self.try_send(buf)
+        }
+
+        #[inline]
+        fn is_write_vectored(&self) -> bool {
+            false
+        }
+
+        /// Datagram socket have no write side to shutdown, this does nothing.
+        #[inline]
+        fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
     }
 
     #[cfg(unix)]
     mod unix {
         use super::*;
-        use tokio::net::UnixStream;
+        use std::{io::Write, os::fd::AsRawFd};
+        use tokio::{
+            io::{Interest, unix::AsyncFd},
+            net::{UnixDatagram, UnixStream, unix},
+        };
+
+        use crate::io::read::fd_file;
+
+        /// Shutdown the write side of socket with shared reference.
+        fn fd_shutdown(io: &impl AsRawFd) -> io::Result<()> {
+            use std::os::{fd::FromRawFd, unix::net::UnixStream};
+            // SAFETY: the file descriptor is valid for the lifetime of `io`, and `ManuallyDrop`
+            // prevents the file descriptor from being closed
+            let stream = ManuallyDrop::new(unsafe { UnixStream::from_raw_fd(io.as_raw_fd()) });
+            stream.shutdown(Shutdown::Write)
+        }
 
         impl AsyncIoWrite for UnixStream {
             #[inline]
@@ -161,8 +277,76 @@ mod tokio_io {
             fn is_write_vectored(&self) -> bool {
                 AsyncWrite::is_write_vectored(self)
             }
+
+            #[inline]
+            fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+                Poll::Ready(fd_shutdown(self))
+            }
         }
-    }
-}
 
+        impl_write_half!(UnixStream, fd_shutdown => unix::OwnedWriteHalf, unix::WriteHalf<'_>);
+
+        /// Datagram socket send a single datagram for each write.
+        impl AsyncIoWrite for UnixDatagram {
+            #[inline]
+            fn poll_write_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                self.poll_send_ready(cx)
+            }
+
+            #[inline]
+            fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+                self.try_send(buf)
+            }
+
+            /// Only the first non-empty buffer is sent as a datagram.
+            #[inline]
+            fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+                let buf = bufs.iter().find(|b| !b.is_empty()).map_or(&[][..], |b| &**b);
+                self.try_send(buf)
+            }
+
+            #[inline]
+            fn is_write_vectored(&self) -> bool {
+                false
+            }
+
+            /// Datagram socket have no write side to shutdown, this does nothing.
+            #[inline]
+            fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
 
+        /// The file descriptor must be in non-blocking mode.
+        impl<T: AsRawFd> AsyncIoWrite for AsyncFd<T> {
+            #[inline]
+            fn poll_write_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+                // the guard is dropped without clearing readiness, readiness is cleared by
+                // `try_io` when the write returns `WouldBlock`
+                self.poll_write_ready(cx).map_ok(drop)
+            }
+
+            #[inline]
+            fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+                self.try_io(Interest::WRITABLE, |fd| fd_file(fd).write(buf))
+            }
+
+            #[inline]
+            fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+                self.try_io(Interest::WRITABLE, |fd| fd_file(fd).write_vectored(bufs))
+            }
+
+            #[inline]
+            fn is_write_vectored(&self) -> bool {
+                true
+            }
+
+            /// Shutdown the write side of the socket, returns error if the file descriptor is not
+            /// a socket.
+            #[inline]
+            fn poll_shutdown(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+                Poll::Ready(fd_shutdown(self))
+            }
+        }
+    }
+}
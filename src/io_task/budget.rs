@@ -0,0 +1,135 @@
+use std::{
+    io,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Poll, Waker},
+};
+
+/// Shared write queue budget between [`IoTask`][super::IoTask] and its handles.
+#[derive(Debug)]
+pub(crate) struct Budget {
+    max_bytes: usize,
+    max_writes: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    bytes: usize,
+    writes: usize,
+    closed: bool,
+    /// Wakers of pending waiters with the waiter id.
+    wakers: Vec<(u64, Waker)>,
+    next_id: u64,
+}
+
+impl Budget {
+    pub(crate) fn new(max_bytes: usize, max_writes: usize) -> Self {
+        Self { max_bytes, max_writes, state: Mutex::default() }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn has_capacity(&self, state: &State) -> bool {
+        state.bytes < self.max_bytes && state.writes < self.max_writes
+    }
+
+    /// Creates new waiter for the write queue limit.
+    pub(crate) fn waiter(self: &Arc<Self>) -> Waiter {
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        Waiter { budget: self.clone(), id }
+    }
+
+    /// Account a queued write.
+    pub(crate) fn acquire(&self, len: usize) {
+        let mut state = self.lock();
+        state.bytes += len;
+        state.writes += 1;
+    }
+
+    /// Release a completed write.
+    pub(crate) fn release(&self, len: usize) {
+        let mut state = self.lock();
+        state.bytes -= len;
+        state.writes -= 1;
+        if self.has_capacity(&state) {
+            wake_all(&mut state);
+        }
+    }
+
+    /// Close the budget, all pending and future readiness poll returns error.
+    pub(crate) fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        wake_all(&mut state);
+    }
+}
+
+fn wake_all(state: &mut State) {
+    for (_, waker) in state.wakers.drain(..) {
+        waker.wake();
+    }
+}
+
+/// Waiter of the write queue limit.
+///
+/// Each waiter registers at most one waker, which is removed when the waiter is dropped.
+#[derive(Debug)]
+pub(crate) struct Waiter {
+    budget: Arc<Budget>,
+    id: u64,
+}
+
+impl Waiter {
+    /// Poll until the write queue is below the limit.
+    pub(crate) fn poll_ready(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let mut state = self.budget.lock();
+        if state.closed {
+            return Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()));
+        }
+        if self.budget.has_capacity(&state) {
+            return Poll::Ready(Ok(()));
+        }
+        match state.wakers.iter_mut().find(|(id, _)| *id == self.id) {
+            Some((_, waker)) => waker.clone_from(cx.waker()),
+            None => state.wakers.push((self.id, cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        let mut state = self.budget.lock();
+        state.wakers.retain(|(id, _)| *id != self.id);
+    }
+}
+
+#[test]
+fn test_budget_waiter() {
+    let mut cx = std::task::Context::from_waker(Waker::noop());
+    let budget = Arc::new(Budget::new(4, usize::MAX));
+    budget.acquire(4);
+
+    // dropped waiter does not leave its waker
+    for _ in 0..4 {
+        let mut waiter = budget.waiter();
+        assert!(waiter.poll_ready(&mut cx).is_pending());
+        assert!(waiter.poll_ready(&mut cx).is_pending());
+    }
+    assert!(budget.lock().wakers.is_empty());
+
+    let mut waiter = budget.waiter();
+    assert!(waiter.poll_ready(&mut cx).is_pending());
+    assert_eq!(budget.lock().wakers.len(), 1);
+
+    budget.release(4);
+    assert!(budget.lock().wakers.is_empty());
+    assert!(matches!(waiter.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+
+    budget.close();
+    assert!(matches!(waiter.poll_ready(&mut cx), Poll::Ready(Err(_))));
+}
@@ -0,0 +1,230 @@
+use bytes::{Bytes, BytesMut};
+use std::sync::Arc;
+#[cfg(feature = "tokio")]
+use std::time::Duration;
+
+use super::{
+    Budget, IoHandle, IoPoll, IoTask, MuxHandle, Observer,
+    mux::Mux,
+    stats::SharedStats,
+    task::TaskTx,
+    timeout::Timeouts,
+};
+use crate::{
+    codec::{Decoder, Encoder},
+    io::{AsyncIoRead, AsyncIoWrite},
+};
+
+/// [`IoTask`] builder.
+///
+/// By default, the write queue is unbounded, there is no timeout, and at most 64 frames of each
+/// stream is buffered in multiplexing mode.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use tcio::io::{AsyncIoRead, AsyncIoWrite};
+/// # async fn app(io: impl AsyncIoRead + AsyncIoWrite) -> std::io::Result<()> {
+/// use tcio::io_task::Builder;
+///
+/// let (handle, task) = Builder::new()
+///     .max_queued_bytes(64 * 1024)
+///     .max_queued_writes(32)
+///     .build_handle(io);
+///
+/// // the task must be spawned to drive the io
+/// # fn spawn<T>(_: T) { }
+/// spawn(task);
+///
+/// handle.write_ready().await?;
+/// handle.write(bytes::Bytes::from_static(b"Hello"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Builder {
+    max_queued_bytes: usize,
+    max_queued_writes: usize,
+    max_buffered_frames: usize,
+    timeouts: Timeouts,
+    observer: Option<Arc<dyn Observer>>,
+}
+
+impl Builder {
+    /// Creates new [`Builder`].
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            max_queued_bytes: usize::MAX,
+            max_queued_writes: usize::MAX,
+            max_buffered_frames: 64,
+            timeouts: Timeouts::default(),
+            observer: None,
+        }
+    }
+
+    /// Set the maximum bytes in the write queue.
+    ///
+    /// See [module level docs][super#backpressure] for more details.
+    #[inline]
+    pub fn max_queued_bytes(&mut self, max: usize) -> &mut Self {
+        self.max_queued_bytes = max;
+        self
+    }
+
+    /// Set the maximum number of writes in the write queue.
+    ///
+    /// See [module level docs][super#backpressure] for more details.
+    #[inline]
+    pub fn max_queued_writes(&mut self, max: usize) -> &mut Self {
+        self.max_queued_writes = max;
+        self
+    }
+
+    /// Set the maximum number of buffered frames of each stream in multiplexing mode.
+    ///
+    /// When a stream reached the limit, the io is not read until the stream frames is read or
+    /// the stream is closed.
+    #[inline]
+    pub fn max_buffered_frames(&mut self, max: usize) -> &mut Self {
+        self.max_buffered_frames = max;
+        self
+    }
+
+    /// Set the maximum duration without data read from the io.
+    ///
+    /// See [module level docs][super#timeouts] for more details.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn read_idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeouts.read_idle = Some(timeout);
+        self
+    }
+
+    /// Set the maximum duration of pending writes without progress.
+    ///
+    /// See [module level docs][super#timeouts] for more details.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn write_stall_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeouts.write_stall = Some(timeout);
+        self
+    }
+
+    /// Set the maximum duration of the task.
+    ///
+    /// See [module level docs][super#timeouts] for more details.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn lifetime_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeouts.lifetime = Some(timeout);
+        self
+    }
+
+    /// Set the observer of the task events.
+    ///
+    /// The observer is shared by all tasks created by this builder.
+    #[inline]
+    pub fn observer<O: Observer>(&mut self, observer: O) -> &mut Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Create new [`IoTask`] with [`IoHandle`] as the handle.
+    pub fn build_handle<IO>(&self, io: IO) -> (IoHandle, IoTask<IO>)
+    where
+        IO: AsyncIoRead + AsyncIoWrite,
+    {
+        let (tx, budget, stats, task) = self.build(io, None);
+        (IoHandle::from_spawned(tx, budget, stats), task)
+    }
+
+    /// Create new [`IoTask`] with [`IoPoll`] as the handle.
+    pub fn build_poll<IO>(&self, io: IO) -> (IoPoll, IoTask<IO>)
+    where
+        IO: AsyncIoRead + AsyncIoWrite,
+    {
+        let (tx, budget, stats, task) = self.build(io, None);
+        (IoPoll::from_spawned(tx, budget, stats), task)
+    }
+
+    /// Create new [`IoTask`] in multiplexing mode with [`MuxHandle`] as the handle.
+    ///
+    /// See [`MuxHandle`] for more details.
+    pub fn build_mux<IO, C>(&self, io: IO, codec: C) -> (MuxHandle, IoTask<IO>)
+    where
+        IO: AsyncIoRead + AsyncIoWrite,
+        C: Decoder<Item = (u64, BytesMut)> + Encoder<(u64, Bytes)> + Send + 'static,
+    {
+        let mux = Mux::new(Box::new(codec), self.max_buffered_frames);
+        let (tx, budget, stats, task) = self.build(io, Some(mux));
+        (MuxHandle::from_spawned(tx, budget, stats), task)
+    }
+
+    fn build<IO>(&self, io: IO, mux: Option<Mux>) -> (TaskTx, Arc<Budget>, SharedStats, IoTask<IO>)
+    where
+        IO: AsyncIoRead + AsyncIoWrite,
+    {
+        let budget = Arc::new(Budget::new(self.max_queued_bytes, self.max_queued_writes));
+        let stats = SharedStats::default();
+        let (tx, task) = IoTask::new(
+            io,
+            budget.clone(),
+            self.timeouts,
+            stats.clone(),
+            self.observer.clone(),
+            mux,
+        );
+        (tx, budget, stats, task)
+    }
+}
+
+impl std::fmt::Debug for Builder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Builder")
+            .field("max_queued_bytes", &self.max_queued_bytes)
+            .field("max_queued_writes", &self.max_queued_writes)
+            .field("max_buffered_frames", &self.max_buffered_frames)
+            .field("timeouts", &self.timeouts)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl Default for Builder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_backpressure() {
+    use bytes::Bytes;
+    use std::pin::pin;
+
+    use crate::io::mock;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new().write(b"Foo").would_block().write(b"Bar").build();
+    let (handle, task) = Builder::new().max_queued_writes(1).build_handle(io);
+    let mut task = pin!(task);
+
+    assert!(pin!(handle.write_ready()).poll(&mut cx).is_ready());
+    handle.write(Bytes::from_static(b"Foo"));
+    assert!(pin!(handle.write_ready()).poll(&mut cx).is_pending());
+
+    // write completed
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(pin!(handle.write_ready()).poll(&mut cx).is_ready());
+
+    // write is pending
+    handle.write(Bytes::from_static(b"Bar"));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(pin!(handle.write_ready()).poll(&mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(pin!(handle.write_ready()).poll(&mut cx).is_ready());
+
+    drop(handle);
+    assert!(task.as_mut().poll(&mut cx).is_ready());
+}
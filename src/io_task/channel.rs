@@ -0,0 +1,292 @@
+//! Runtime agnostic channels used between [`IoTask`][super::IoTask] and its handles.
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Error returned when the other half of the channel is dropped.
+#[derive(Debug)]
+pub(crate) struct Closed;
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Multi producer, single consumer unbounded channel.
+pub(crate) mod mpsc {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        task::{Poll, Waker},
+    };
+
+    use super::lock;
+
+    struct State<T> {
+        queue: VecDeque<T>,
+        senders: usize,
+        rx_closed: bool,
+        waker: Option<Waker>,
+    }
+
+    pub(crate) fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+        let state = Arc::new(Mutex::new(State {
+            queue: VecDeque::new(),
+            senders: 1,
+            rx_closed: false,
+            waker: None,
+        }));
+        (UnboundedSender { state: state.clone() }, UnboundedReceiver { state })
+    }
+
+    pub(crate) struct UnboundedSender<T> {
+        state: Arc<Mutex<State<T>>>,
+    }
+
+    impl<T> UnboundedSender<T> {
+        /// Send a message, returns the message back if the receiver is dropped.
+        pub(crate) fn send(&self, msg: T) -> Result<(), T> {
+            let mut state = lock(&self.state);
+            if state.rx_closed {
+                return Err(msg);
+            }
+            state.queue.push_back(msg);
+            let waker = state.waker.take();
+            drop(state);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+            Ok(())
+        }
+
+        /// Returns `true` if the receiver is dropped.
+        pub(crate) fn is_closed(&self) -> bool {
+            lock(&self.state).rx_closed
+        }
+    }
+
+    impl<T> Clone for UnboundedSender<T> {
+        fn clone(&self) -> Self {
+            lock(&self.state).senders += 1;
+            Self { state: self.state.clone() }
+        }
+    }
+
+    impl<T> Drop for UnboundedSender<T> {
+        fn drop(&mut self) {
+            let mut state = lock(&self.state);
+            state.senders -= 1;
+            let waker = match state.senders {
+                0 => state.waker.take(),
+                _ => None,
+            };
+            drop(state);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    pub(crate) struct UnboundedReceiver<T> {
+        state: Arc<Mutex<State<T>>>,
+    }
+
+    impl<T> UnboundedReceiver<T> {
+        /// Poll for the next message, returns `None` if all senders is dropped.
+        pub(crate) fn poll_recv(&mut self, cx: &mut std::task::Context) -> Poll<Option<T>> {
+            let mut state = lock(&self.state);
+            if let Some(msg) = state.queue.pop_front() {
+                return Poll::Ready(Some(msg));
+            }
+            if state.senders == 0 {
+                return Poll::Ready(None);
+            }
+            match &mut state.waker {
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => state.waker = Some(cx.waker().clone()),
+            }
+            Poll::Pending
+        }
+    }
+
+    impl<T> UnboundedReceiver<T> {
+        /// Close the channel and returns the queued messages, further sends returns error.
+        pub(crate) fn close(&mut self) -> VecDeque<T> {
+            let mut state = lock(&self.state);
+            state.rx_closed = true;
+            std::mem::take(&mut state.queue)
+        }
+    }
+
+    impl<T> Drop for UnboundedReceiver<T> {
+        fn drop(&mut self) {
+            let mut state = lock(&self.state);
+            state.rx_closed = true;
+            let queue = std::mem::take(&mut state.queue);
+            // messages may contains other channels, drop them outside the lock
+            drop(state);
+            drop(queue);
+        }
+    }
+
+    impl<T> std::fmt::Debug for UnboundedSender<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.debug_struct("UnboundedSender").finish_non_exhaustive()
+        }
+    }
+
+    impl<T> std::fmt::Debug for UnboundedReceiver<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.debug_struct("UnboundedReceiver").finish_non_exhaustive()
+        }
+    }
+}
+
+/// Single value channel.
+pub(crate) mod oneshot {
+    use std::{
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Poll, Waker},
+    };
+
+    use super::{Closed, lock};
+
+    struct State<T> {
+        value: Option<T>,
+        tx_closed: bool,
+        rx_closed: bool,
+        waker: Option<Waker>,
+    }
+
+    pub(crate) fn channel<T>() -> (Sender<T>, Receiver<T>) {
+        let state = Arc::new(Mutex::new(State {
+            value: None,
+            tx_closed: false,
+            rx_closed: false,
+            waker: None,
+        }));
+        (Sender { state: state.clone() }, Receiver { state })
+    }
+
+    pub(crate) struct Sender<T> {
+        state: Arc<Mutex<State<T>>>,
+    }
+
+    impl<T> Sender<T> {
+        /// Send the value, returns the value back if the receiver is dropped.
+        pub(crate) fn send(self, value: T) -> Result<(), T> {
+            let mut state = lock(&self.state);
+            if state.rx_closed {
+                return Err(value);
+            }
+            // the receiver is woken up on drop
+            state.value = Some(value);
+            Ok(())
+        }
+
+        /// Returns `true` if the receiver is dropped.
+        pub(crate) fn is_closed(&self) -> bool {
+            lock(&self.state).rx_closed
+        }
+    }
+
+    impl<T> Drop for Sender<T> {
+        fn drop(&mut self) {
+            let mut state = lock(&self.state);
+            state.tx_closed = true;
+            let waker = state.waker.take();
+            drop(state);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    pub(crate) struct Receiver<T> {
+        state: Arc<Mutex<State<T>>>,
+    }
+
+    impl<T> Future for Receiver<T> {
+        type Output = Result<T, Closed>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+            let mut state = lock(&self.state);
+            if let Some(value) = state.value.take() {
+                return Poll::Ready(Ok(value));
+            }
+            if state.tx_closed {
+                return Poll::Ready(Err(Closed));
+            }
+            match &mut state.waker {
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => state.waker = Some(cx.waker().clone()),
+            }
+            Poll::Pending
+        }
+    }
+
+    impl<T> Drop for Receiver<T> {
+        fn drop(&mut self) {
+            let mut state = lock(&self.state);
+            state.rx_closed = true;
+            let value = state.value.take();
+            // value may contains other channels, drop it outside the lock
+            drop(state);
+            drop(value);
+        }
+    }
+
+    impl<T> std::fmt::Debug for Sender<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.debug_struct("Sender").finish_non_exhaustive()
+        }
+    }
+
+    impl<T> std::fmt::Debug for Receiver<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.debug_struct("Receiver").finish_non_exhaustive()
+        }
+    }
+}
+
+#[test]
+fn test_channel() {
+    use std::{pin::pin, task::Poll};
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+
+    // mpsc
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let tx2 = tx.clone();
+    assert!(tx.send(1).is_ok());
+    assert!(tx2.send(2).is_ok());
+    assert!(matches!(rx.poll_recv(&mut cx), Poll::Ready(Some(1))));
+    assert!(matches!(rx.poll_recv(&mut cx), Poll::Ready(Some(2))));
+    assert!(rx.poll_recv(&mut cx).is_pending());
+    drop((tx, tx2));
+    assert!(matches!(rx.poll_recv(&mut cx), Poll::Ready(None)));
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    drop(rx);
+    assert!(tx.is_closed());
+    assert!(matches!(tx.send(1), Err(1)));
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    assert!(tx.send(1).is_ok());
+    assert_eq!(rx.close(), [1]);
+    assert!(matches!(tx.send(2), Err(2)));
+
+    // oneshot
+    let (tx, rx) = oneshot::channel();
+    let mut rx = pin!(rx);
+    assert!(rx.as_mut().poll(&mut cx).is_pending());
+    assert!(tx.send(1).is_ok());
+    assert!(matches!(rx.as_mut().poll(&mut cx), Poll::Ready(Ok(1))));
+
+    let (tx, rx) = oneshot::channel::<()>();
+    drop(tx);
+    assert!(matches!(pin!(rx).poll(&mut cx), Poll::Ready(Err(Closed))));
+
+    let (tx, rx) = oneshot::channel();
+    drop(rx);
+    assert!(tx.is_closed());
+    assert!(matches!(tx.send(1), Err(1)));
+}
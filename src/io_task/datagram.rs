@@ -0,0 +1,359 @@
+use bytes::{Bytes, BytesMut};
+use std::{
+    collections::VecDeque,
+    io,
+    mem::take,
+    pin::Pin,
+    task::{Poll, ready},
+};
+
+use super::{
+    SendTo,
+    channel::{
+        mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+        oneshot::{Receiver, Sender, channel},
+    },
+    task::TaskResultTx,
+};
+use crate::io::{AsyncDatagramRecv, AsyncDatagramSend};
+
+/// Capacity of the receive buffer, which is the maximum size of UDP datagram.
+const MAX_DATAGRAM_SIZE: usize = 64 * 1024;
+
+type RecvTx<A> = Sender<io::Result<(BytesMut, A)>>;
+type RecvRx<A> = Receiver<io::Result<(BytesMut, A)>>;
+
+enum DatagramMessage<A> {
+    /// Receive a single datagram.
+    Recv {
+        tx: RecvTx<A>,
+    },
+    /// Send a single datagram to the target.
+    ///
+    /// When completed, the result will be send to `tx`.
+    Send {
+        bytes: Bytes,
+        target: A,
+        tx: TaskResultTx,
+    },
+}
+
+/// A task which drive a datagram socket to provide concurrent operation.
+///
+/// Unlike [`IoTask`][super::IoTask], each operation is a whole datagram. The socket is only
+/// received from when there is pending receive, and datagrams larger than 64KiB is truncated.
+///
+/// The task is completed when all handles is dropped and pending sends is completed, and returns
+/// the underlying socket.
+///
+/// Use [`DatagramHandle`] to create the task.
+pub struct DatagramTask<IO: AsyncDatagramRecv> {
+    rx: UnboundedReceiver<DatagramMessage<IO::Addr>>,
+    /// `None` after the task completed.
+    io: Option<IO>,
+    /// Reused receive buffer, received datagram is copied out so it does not hold the buffer.
+    buffer: BytesMut,
+    /// All handles is dropped.
+    rx_closed: bool,
+    recv_queue: VecDeque<RecvTx<IO::Addr>>,
+    /// Received datagram which its receiver is dropped, returned by the next receive.
+    unclaimed: Option<(BytesMut, IO::Addr)>,
+    send_queue: VecDeque<(Bytes, IO::Addr, TaskResultTx)>,
+}
+
+impl<IO: AsyncDatagramRecv> Unpin for DatagramTask<IO> {}
+
+impl<IO> DatagramTask<IO>
+where
+    IO: AsyncDatagramRecv + AsyncDatagramSend<Addr = <IO as AsyncDatagramRecv>::Addr>,
+{
+    fn poll_message(&mut self, cx: &mut std::task::Context) {
+        while !self.rx_closed {
+            match self.rx.poll_recv(cx) {
+                Poll::Ready(Some(DatagramMessage::Recv { tx })) => self.recv_queue.push_back(tx),
+                Poll::Ready(Some(DatagramMessage::Send { bytes, target, tx })) => {
+                    self.send_queue.push_back((bytes, target, tx));
+                }
+                Poll::Ready(None) => self.rx_closed = true,
+                Poll::Pending => return,
+            }
+        }
+    }
+
+    fn poll_recv(&mut self, cx: &mut std::task::Context) {
+        let io = self.io.as_ref().unwrap();
+        loop {
+            self.recv_queue.retain(|tx| !tx.is_closed());
+            let Some(tx) = self.recv_queue.pop_front() else {
+                return;
+            };
+
+            let datagram = match self.unclaimed.take() {
+                Some(datagram) => Ok(datagram),
+                None => {
+                    self.buffer.clear();
+                    self.buffer.reserve(MAX_DATAGRAM_SIZE);
+                    match io.poll_recv_from(&mut self.buffer, cx) {
+                        Poll::Ready(result) => {
+                            result.map(|(_, addr)| (BytesMut::from(&self.buffer[..]), addr))
+                        }
+                        Poll::Pending => {
+                            self.recv_queue.push_front(tx);
+                            return;
+                        }
+                    }
+                }
+            };
+
+            if let Err(Ok(datagram)) = tx.send(datagram) {
+                self.unclaimed = Some(datagram);
+            }
+        }
+    }
+
+    fn poll_send(&mut self, cx: &mut std::task::Context) {
+        let io = self.io.as_ref().unwrap();
+        while let Some((bytes, target, _)) = self.send_queue.front() {
+            let result = match io.poll_send_to(bytes, target, cx) {
+                Poll::Ready(Ok(sent)) if sent < bytes.len() => Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "datagram is not fully sent",
+                )),
+                Poll::Ready(Ok(_)) => Ok(()),
+                Poll::Ready(Err(err)) => Err(err),
+                Poll::Pending => return,
+            };
+            let (_, _, tx) = self.send_queue.pop_front().unwrap();
+            let _ = tx.send(result);
+        }
+    }
+}
+
+impl<IO> Future for DatagramTask<IO>
+where
+    IO: AsyncDatagramRecv + AsyncDatagramSend<Addr = <IO as AsyncDatagramRecv>::Addr>,
+{
+    type Output = IO;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        let me = self.get_mut();
+        assert!(me.io.is_some(), "`DatagramTask` polled after completion");
+
+        me.poll_message(cx);
+        me.poll_recv(cx);
+        me.poll_send(cx);
+
+        if me.rx_closed && me.send_queue.is_empty() {
+            // receivers may still exists if the handles is dropped before its futures
+            for tx in take(&mut me.recv_queue) {
+                let _ = tx.send(Err(io::ErrorKind::ConnectionAborted.into()));
+            }
+            return Poll::Ready(me.io.take().unwrap());
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<IO: AsyncDatagramRecv> std::fmt::Debug for DatagramTask<IO> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("DatagramTask")
+            .field("queued_recvs", &self.recv_queue.len())
+            .field("queued_sends", &self.send_queue.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A stateless [`DatagramTask`] handle.
+///
+/// All operations only requires shared reference, which returns the statefull [`Future`].
+pub struct DatagramHandle<A> {
+    tx: UnboundedSender<DatagramMessage<A>>,
+}
+
+impl<A> DatagramHandle<A> {
+    /// Create new [`DatagramTask`] with [`DatagramHandle`] as the handle.
+    pub fn new<IO>(io: IO) -> (DatagramHandle<A>, DatagramTask<IO>)
+    where
+        IO: AsyncDatagramRecv<Addr = A> + AsyncDatagramSend<Addr = A>,
+    {
+        let (tx, rx) = unbounded_channel();
+        let task = DatagramTask {
+            rx,
+            io: Some(io),
+            buffer: BytesMut::new(),
+            rx_closed: false,
+            recv_queue: VecDeque::new(),
+            unclaimed: None,
+            send_queue: VecDeque::new(),
+        };
+        (DatagramHandle { tx }, task)
+    }
+
+    /// Returns `true` if the datagram task is already closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// Receive a single datagram with the sender address.
+    pub fn recv_from(&self) -> RecvFrom<A> {
+        let (tx, rx) = channel();
+        let repr = match self.tx.send(DatagramMessage::Recv { tx }) {
+            Ok(()) => Ok(rx),
+            Err(_) => Err(Some(io::ErrorKind::ConnectionAborted.into())),
+        };
+        RecvFrom { repr }
+    }
+
+    /// Send a single datagram to the target.
+    ///
+    /// Note that the send is queued even if the future is dropped.
+    pub fn send_to(&self, bytes: Bytes, target: A) -> SendTo {
+        let (tx, rx) = channel();
+        SendTo::new(self.tx.send(DatagramMessage::Send { bytes, target, tx }), rx)
+    }
+}
+
+impl<A> Clone for DatagramHandle<A> {
+    fn clone(&self) -> Self {
+        Self { tx: self.tx.clone() }
+    }
+}
+
+impl<A> std::fmt::Debug for DatagramHandle<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("DatagramHandle").finish_non_exhaustive()
+    }
+}
+
+/// Future returned from [`recv_from`][DatagramHandle::recv_from].
+///
+/// This future is cancel safe as long as the datagram is not yet received, if it is dropped
+/// before that, the datagram is returned by the next receive.
+pub struct RecvFrom<A> {
+    repr: Result<RecvRx<A>, Option<io::Error>>,
+}
+
+impl<A> Future for RecvFrom<A> {
+    type Output = io::Result<(BytesMut, A)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        let result = match &mut self.repr {
+            Ok(rx) => match ready!(Pin::new(rx).poll(cx)) {
+                Ok(result) => result,
+                Err(_) => Err(io::ErrorKind::ConnectionAborted.into()),
+            },
+            Err(err) => Err(err.take().unwrap()),
+        };
+
+        Poll::Ready(result)
+    }
+}
+
+impl<A> std::fmt::Debug for RecvFrom<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RecvFrom").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_datagram() {
+    use tokio::net::UdpSocket;
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+
+    rt.block_on(async {
+        let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let a_addr = a.local_addr().unwrap();
+        let b_addr = b.local_addr().unwrap();
+
+        let (a, task_a) = DatagramHandle::new(a);
+        let (b, task_b) = DatagramHandle::new(b);
+        let task_a = tokio::spawn(task_a);
+        let task_b = tokio::spawn(task_b);
+
+        // dropped receive does not lose the datagram
+        let cancelled = b.recv_from();
+        let recv = b.recv_from();
+        drop(cancelled);
+
+        a.send_to(Bytes::from_static(b"Foo"), b_addr).await.unwrap();
+        a.send_to(Bytes::from_static(b"BarBaz"), b_addr).await.unwrap();
+
+        let (data, addr) = recv.await.unwrap();
+        assert_eq!(&data[..], b"Foo");
+        assert_eq!(addr, a_addr);
+        let (data, addr) = b.recv_from().await.unwrap();
+        assert_eq!(&data[..], b"BarBaz");
+        assert_eq!(addr, a_addr);
+
+        // reply to the sender
+        b.send_to(Bytes::from_static(b"Ok"), addr).await.unwrap();
+        let (data, addr) = a.recv_from().await.unwrap();
+        assert_eq!(&data[..], b"Ok");
+        assert_eq!(addr, b_addr);
+
+        drop((a, b));
+        assert_eq!(task_a.await.unwrap().local_addr().unwrap(), a_addr);
+        assert_eq!(task_b.await.unwrap().local_addr().unwrap(), b_addr);
+    });
+}
+
+#[test]
+fn test_recv_cancel() {
+    use bytes::BufMut;
+    use std::{cell::RefCell, pin::pin};
+
+    /// Socket which receive queued datagrams.
+    struct Socket(RefCell<VecDeque<&'static [u8]>>);
+
+    impl AsyncDatagramRecv for Socket {
+        type Addr = ();
+
+        fn poll_recv_ready(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+            match self.0.borrow().is_empty() {
+                true => Poll::Pending,
+                false => Poll::Ready(Ok(())),
+            }
+        }
+
+        fn try_recv_from(&self, buf: &mut BytesMut) -> io::Result<(usize, ())> {
+            let datagram = self.0.borrow_mut().pop_front().ok_or(io::ErrorKind::WouldBlock)?;
+            buf.put_slice(datagram);
+            Ok((datagram.len(), ()))
+        }
+    }
+
+    impl AsyncDatagramSend for Socket {
+        type Addr = ();
+
+        fn poll_send_ready(&self, _: &mut std::task::Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn try_send_to(&self, buf: &[u8], _: &()) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+    }
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let socket = Socket(RefCell::new(VecDeque::from([&b"Foo"[..], b"Bar"])));
+    let (handle, task) = DatagramHandle::new(socket);
+    let mut task = pin!(task);
+
+    // dropped receive does not lose the datagram
+    drop(handle.recv_from());
+
+    // receives is completed in order, even when awaited out of order
+    let mut foo = pin!(handle.recv_from());
+    let mut bar = pin!(handle.recv_from());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(bar.as_mut().poll(&mut cx), Poll::Ready(Ok((b, ()))) if b == "Bar"));
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok((b, ()))) if b == "Foo"));
+}
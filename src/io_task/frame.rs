@@ -0,0 +1,102 @@
+use bytes::{Buf, BytesMut};
+use std::{io, task::Poll};
+
+use super::channel::oneshot::{Receiver, Sender, channel};
+use crate::{
+    codec::Decoder,
+    io::{AsyncBufRead, BufCursor},
+};
+
+pub(crate) type FrameRx<T> = Receiver<io::Result<Option<T>>>;
+
+/// Type erased frame read request.
+pub(crate) trait FrameRead: Send {
+    /// Returns `true` if the read future is dropped.
+    fn is_cancelled(&self) -> bool;
+
+    /// Decode a frame from the buffer, returns ready when the result is sent.
+    fn poll_frame(
+        &mut self,
+        buffer: &mut BytesMut,
+        eof: bool,
+        cx: &mut std::task::Context,
+    ) -> Poll<()>;
+
+    /// Send an error as the result.
+    fn send_err(&mut self, err: io::Error);
+}
+
+/// Frame read request with the user decoder.
+pub(crate) struct FrameTask<D: Decoder> {
+    decoder: D,
+    tx: Option<Sender<io::Result<Option<D::Item>>>>,
+}
+
+impl<D: Decoder> FrameTask<D> {
+    pub(crate) fn new(decoder: D) -> (Self, FrameRx<D::Item>) {
+        let (tx, rx) = channel();
+        (Self { decoder, tx: Some(tx) }, rx)
+    }
+}
+
+impl<D> FrameRead for FrameTask<D>
+where
+    D: Decoder + Send,
+    D::Item: Send,
+{
+    fn is_cancelled(&self) -> bool {
+        self.tx.as_ref().is_none_or(Sender::is_closed)
+    }
+
+    fn poll_frame(
+        &mut self,
+        buffer: &mut BytesMut,
+        eof: bool,
+        cx: &mut std::task::Context,
+    ) -> Poll<()> {
+        let mut cursor = BufCursor::new(TaskBuffer { buffer, eof });
+        let Poll::Ready(result) = self.decoder.poll_decode(&mut cursor, cx) else {
+            return Poll::Pending;
+        };
+        if let Some(tx) = self.tx.take() {
+            let _ = tx.send(result);
+        }
+        Poll::Ready(())
+    }
+
+    fn send_err(&mut self, err: io::Error) {
+        if let Some(tx) = self.tx.take() {
+            let _ = tx.send(Err(err));
+        }
+    }
+}
+
+/// [`AsyncBufRead`] over the task buffer.
+///
+/// Filling the buffer returns pending, the task will retry decoding when more data is read, or
+/// returns end of stream if the io reached end of stream.
+pub(crate) struct TaskBuffer<'a> {
+    pub(crate) buffer: &'a mut BytesMut,
+    pub(crate) eof: bool,
+}
+
+impl AsyncBufRead for TaskBuffer<'_> {
+    fn poll_read_fill(&mut self, _: &mut std::task::Context) -> Poll<io::Result<usize>> {
+        match self.eof {
+            true => Poll::Ready(Ok(0)),
+            false => Poll::Pending,
+        }
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.buffer
+    }
+
+    fn consume(&mut self, cnt: usize) {
+        self.buffer.advance(cnt);
+    }
+
+    fn split_to(&mut self, cnt: usize) -> BytesMut {
+        self.buffer.split_to(cnt)
+    }
+}
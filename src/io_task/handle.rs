@@ -2,15 +2,22 @@ use bytes::{Bytes, BytesMut};
 use std::{
     io,
     pin::Pin,
+    sync::Arc,
     task::{Poll, ready},
 };
-use tokio::sync::oneshot::channel;
 
-use crate::io::{AsyncIoRead, AsyncIoWrite};
+use crate::{
+    ByteStr,
+    codec::{Decoder, LinesCodec},
+    io::{AsyncIoRead, AsyncIoWrite},
+};
 
 use super::{
-    IoTask, TaskReadMessage, TaskSyncMessage, TaskTxMessage,
-    task::{TaskReadRx, TaskSyncRx, TaskTx},
+    Budget, Builder, IoTask, TaskReadMessage, TaskTxMessage,
+    channel::oneshot::channel,
+    frame::{FrameRx, FrameTask},
+    stats::{self, SharedStats, Stats},
+    task::{TaskReadRx, TaskResultRx, TaskTx},
 };
 
 /// A stateless [`IoTask`] handle.
@@ -22,21 +29,24 @@ use super::{
 #[derive(Debug, Clone)]
 pub struct IoHandle {
     tx: TaskTx,
+    budget: Arc<Budget>,
+    stats: SharedStats,
 }
 
 impl IoHandle {
-    pub(crate) fn from_spawned(tx: TaskTx) -> Self {
-        Self { tx }
+    pub(crate) fn from_spawned(tx: TaskTx, budget: Arc<Budget>, stats: SharedStats) -> Self {
+        Self { tx, budget, stats }
     }
 
     /// Create new [`IoTask`] with [`IoHandle`] as the handle.
+    ///
+    /// To create a bounded [`IoTask`], use [`Builder`].
     #[inline]
     pub fn new<IO>(io: IO) -> (IoHandle, IoTask<IO>)
     where
         IO: AsyncIoRead + AsyncIoWrite,
     {
-        let (tx, task) = IoTask::new(io);
-        (Self { tx }, task)
+        Builder::new().build_handle(io)
     }
 
     // ===== Public =====
@@ -47,54 +57,187 @@ impl IoHandle {
         self.tx.is_closed()
     }
 
+    /// Returns snapshot of the [`IoTask`] statistics.
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        stats::snapshot(&self.stats)
+    }
+
     /// Read bytes from the underlying IO.
+    ///
+    /// Returns empty bytes if the underlying IO reached end of stream.
     #[inline]
     pub fn read(&self) -> Read {
         self.read_inner(None)
     }
 
     /// Read exact size bytes from the underlying IO.
+    ///
+    /// Returns [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error if the underlying IO reached
+    /// end of stream before `len` bytes is read.
     #[inline]
     pub fn read_exact(&self, len: usize) -> Read {
         self.read_inner(Some(len))
     }
 
+    /// Read bytes from the underlying IO until the delimiter is found, including the delimiter.
+    ///
+    /// If the underlying IO reached end of stream, returns the remaining bytes without the
+    /// delimiter, which is empty if there is no more bytes.
+    ///
+    /// Note that there is no limit of the read bytes, use [`read_frame`][IoHandle::read_frame]
+    /// with a limited decoder to limit it.
+    pub fn read_until(&self, delim: u8) -> Read {
+        let (tx, rx) = channel();
+        Read::new(self.tx.send(TaskTxMessage::ReadUntil { delim, tx }), rx)
+    }
+
+    /// Read a line from the underlying IO with maximum line length.
+    ///
+    /// This is a shorthand of [`read_frame`][IoHandle::read_frame] with [`LinesCodec`].
+    ///
+    /// Note that each read uses new codec, if the line exceeds `max_length` before its delimiter
+    /// is read, only the read part of the line is discarded and the next read continues from
+    /// the rest of the line.
+    #[inline]
+    pub fn read_line(&self, max_length: usize) -> ReadFrame<ByteStr> {
+        self.read_frame(LinesCodec::with_max_length(max_length))
+    }
+
+    /// Read a frame from the underlying IO using the given decoder.
+    ///
+    /// The decoder runs inside the [`IoTask`] against its read buffer, so only complete frame is
+    /// send back. Decoder state is not shared between reads.
+    ///
+    /// Returns `None` if the underlying IO reached end of stream at frame boundary.
+    pub fn read_frame<D>(&self, decoder: D) -> ReadFrame<D::Item>
+    where
+        D: Decoder + Send + 'static,
+        D::Item: Send + 'static,
+    {
+        let (task, rx) = FrameTask::new(decoder);
+        ReadFrame::new(self.tx.send(TaskTxMessage::ReadFrame { task: Box::new(task) }), rx)
+    }
+
     /// Write bytes to the underlying io.
+    ///
+    /// This does not wait for the write queue limit, use [`write_ready`][IoHandle::write_ready]
+    /// before writing to respect the limit.
     #[inline]
     pub fn write(&self, bytes: Bytes) {
-        let _ = self.tx.send(TaskTxMessage::Write { bytes });
+        let len = bytes.len();
+        self.budget.acquire(len);
+        if self.tx.send(TaskTxMessage::Write { bytes, ack: None }).is_err() {
+            self.budget.release(len);
+        }
     }
 
-    /// Wait for all write operation complete.
+    /// Write bytes to the underlying io, and wait until the bytes is fully written.
+    ///
+    /// Unlike [`write`][IoHandle::write], error of this write is returned by the future instead
+    /// of reported on [`sync`][IoHandle::sync].
+    ///
+    /// Note that the write is queued even if the future is dropped.
+    pub fn write_acked(&self, bytes: Bytes) -> WriteAcked {
+        let len = bytes.len();
+        let (tx, rx) = channel();
+        self.budget.acquire(len);
+        let sent = self.tx.send(TaskTxMessage::Write { bytes, ack: Some(tx) });
+        if sent.is_err() {
+            self.budget.release(len);
+        }
+        WriteAcked::new(sent, rx)
+    }
+
+    /// Write a request to the underlying io, and read the response frame using the given decoder.
+    ///
+    /// The write and the read is queued at once, so the response is paired with the request even
+    /// when the handle is shared with concurrent callers. This assumes the protocol responds in
+    /// the request order, and other reads is not mixed with the calls.
+    ///
+    /// Like [`write`][IoHandle::write], error of the write is reported on
+    /// [`sync`][IoHandle::sync], and the write is queued even if the future is dropped. If the
+    /// write is failed, the future also returns the write error.
+    pub fn call<D>(&self, request: Bytes, decoder: D) -> ReadFrame<D::Item>
+    where
+        D: Decoder + Send + 'static,
+        D::Item: Send + 'static,
+    {
+        let len = request.len();
+        let (task, rx) = FrameTask::new(decoder);
+        self.budget.acquire(len);
+        let sent = self.tx.send(TaskTxMessage::Call { bytes: request, task: Box::new(task) });
+        if sent.is_err() {
+            self.budget.release(len);
+        }
+        ReadFrame::new(sent, rx)
+    }
+
+    /// Wait until the write queue is below the limit.
+    ///
+    /// See [module level docs][super#backpressure] for more details.
+    #[inline]
+    pub fn write_ready(&self) -> impl Future<Output = io::Result<()>> {
+        let mut waiter = self.budget.waiter();
+        std::future::poll_fn(move |cx| waiter.poll_ready(cx))
+    }
+
+    /// Wait for all writes requested before this call to complete.
+    ///
+    /// Returns the first error of those writes, which is not yet reported by previous sync,
+    /// shutdown or detach. Error of [`write_acked`][IoHandle::write_acked] is not reported here.
     #[inline]
     pub fn sync(&self) -> Sync {
         let (tx, rx) = channel();
-        match self.tx.send(TaskTxMessage::Sync { tx }) {
-            Ok(()) => Sync { repr: Repr::Ok(rx) },
-            Err(_) => Sync::closed(),
-        }
+        Sync::new(self.tx.send(TaskTxMessage::Sync { tx }), rx)
+    }
+
+    /// Shutdown the write side of the underlying io after all pending writes is completed.
+    ///
+    /// Reading is still possible after shutdown, writing after shutdown will returns
+    /// [`BrokenPipe`][io::ErrorKind::BrokenPipe] error on the next sync.
+    ///
+    /// Returns error if any pending write failed.
+    #[inline]
+    pub fn shutdown(&self) -> Shutdown {
+        let (tx, rx) = channel();
+        Shutdown::new(self.tx.send(TaskTxMessage::Shutdown { tx }), rx)
+    }
+
+    /// Stop the [`IoTask`] after all pending writes is completed.
+    ///
+    /// When completed, [`IoTask`] returns the underlying io and any buffered bytes that is not
+    /// read, which allows the io to be upgraded to other protocol. Operations requested after
+    /// detach is not processed, and pending reads returns
+    /// [`ConnectionAborted`][io::ErrorKind::ConnectionAborted] error.
+    ///
+    /// Returns error if any pending write failed.
+    #[inline]
+    pub fn detach(&self) -> Detach {
+        let (tx, rx) = channel();
+        Detach::new(self.tx.send(TaskTxMessage::Detach { tx }), rx)
     }
 
     /// Convert into polling based [`IoPoll`][super::IoPoll].
     #[inline]
     pub fn into_polling(self) -> super::IoPoll {
-        super::IoPoll::from_spawned(self.tx)
+        super::IoPoll::from_spawned(self.tx, self.budget, self.stats)
     }
 
     // ===== Inner =====
 
     fn read_inner(&self, cap: Option<usize>) -> Read {
         let (tx, rx) = channel();
-        match self.tx.send(TaskTxMessage::Read { cap, tx }) {
-            Ok(()) => Read { repr: Repr::Ok(rx) },
-            Err(_) => Read::closed(),
-        }
+        Read::new(self.tx.send(TaskTxMessage::Read { cap, tx }), rx)
     }
 }
 
 // ===== Read Future =====
 
 /// Future returned from [`read`][IoHandle::read].
+///
+/// This future is cancel safe, if it is dropped before completion, no data is lost and the data
+/// is returned by the next read.
 pub struct Read {
     repr: Repr<TaskReadRx>,
 }
@@ -105,6 +248,14 @@ enum Repr<T> {
 }
 
 impl Read {
+    /// Creates [`Read`] with the result of sending the request.
+    pub(super) fn new<E>(sent: Result<(), E>, rx: TaskReadRx) -> Self {
+        match sent {
+            Ok(()) => Self { repr: Repr::Ok(rx) },
+            Err(_) => Self::closed(),
+        }
+    }
+
     fn closed() -> Self {
         Self {
             repr: Repr::Err(Some(io::ErrorKind::ConnectionAborted.into())),
@@ -135,30 +286,30 @@ impl std::fmt::Debug for Read {
     }
 }
 
-// ===== Sync Future =====
-
-/// Future returned from [`sync`][IoHandle::sync].
-pub struct Sync {
-    repr: Repr<TaskSyncRx>,
+/// Future returned from [`read_frame`][IoHandle::read_frame] and [`call`][IoHandle::call].
+///
+/// This future is cancel safe as long as the frame is not yet decoded.
+pub struct ReadFrame<T> {
+    repr: Repr<FrameRx<T>>,
 }
 
-impl Sync {
-    fn closed() -> Self {
-        Self {
-            repr: Repr::Err(Some(io::ErrorKind::ConnectionAborted.into())),
-        }
+impl<T> ReadFrame<T> {
+    fn new<E>(sent: Result<(), E>, rx: FrameRx<T>) -> Self {
+        let repr = match sent {
+            Ok(()) => Repr::Ok(rx),
+            Err(_) => Repr::Err(Some(io::ErrorKind::ConnectionAborted.into())),
+        };
+        Self { repr }
     }
 }
 
-impl Future for Sync {
-    type Output = io::Result<()>;
+impl<T> Future for ReadFrame<T> {
+    type Output = io::Result<Option<T>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
         let result = match &mut self.repr {
             Repr::Ok(rx) => match ready!(Pin::new(rx).poll(cx)) {
-                Ok(TaskSyncMessage::Pending) => return Poll::Pending,
-                Ok(TaskSyncMessage::Ok) => Ok(()),
-                Ok(TaskSyncMessage::Err(err)) => Err(err),
+                Ok(result) => result,
                 Err(_) => Err(io::ErrorKind::ConnectionAborted.into()),
             },
             Repr::Err(err) => Err(err.take().unwrap()),
@@ -168,9 +319,77 @@ impl Future for Sync {
     }
 }
 
-impl std::fmt::Debug for Sync {
+impl<T> std::fmt::Debug for ReadFrame<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        f.debug_struct("Sync").finish_non_exhaustive()
+        f.debug_struct("ReadFrame").finish_non_exhaustive()
     }
 }
 
+// ===== Result Futures =====
+
+macro_rules! result_future {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        pub struct $name {
+            repr: Repr<TaskResultRx>,
+        }
+
+        impl $name {
+            /// Creates the future with the result of sending the request.
+            pub(super) fn new<E>(sent: Result<(), E>, rx: TaskResultRx) -> Self {
+                let repr = match sent {
+                    Ok(()) => Repr::Ok(rx),
+                    Err(_) => Repr::Err(Some(io::ErrorKind::ConnectionAborted.into())),
+                };
+                Self { repr }
+            }
+        }
+
+        impl Future for $name {
+            type Output = io::Result<()>;
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+                let result = match &mut self.repr {
+                    Repr::Ok(rx) => match ready!(Pin::new(rx).poll(cx)) {
+                        Ok(result) => result,
+                        Err(_) => Err(io::ErrorKind::ConnectionAborted.into()),
+                    },
+                    Repr::Err(err) => Err(err.take().unwrap()),
+                };
+
+                Poll::Ready(result)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+result_future! {
+    /// Future returned from [`sync`][IoHandle::sync].
+    Sync
+}
+
+result_future! {
+    /// Future returned from [`write_acked`][IoHandle::write_acked].
+    WriteAcked
+}
+
+result_future! {
+    /// Future returned from [`shutdown`][IoHandle::shutdown].
+    Shutdown
+}
+
+result_future! {
+    /// Future returned from [`detach`][IoHandle::detach].
+    Detach
+}
+
+result_future! {
+    /// Future returned from [`send_to`][super::DatagramHandle::send_to].
+    SendTo
+}
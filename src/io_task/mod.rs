@@ -9,17 +9,63 @@
 //! [`IoHandle`] is a stateless handle, all method returns the statefull [`Future`]. Good in
 //! userspace, where `.await` can be just used.
 //!
-//! [`IoHandle`] is a statefull handle, all method is polling based.
+//! [`IoPoll`] is a statefull handle, all method is polling based.
+//!
+//! [`MuxHandle`] is a handle for multiplexing mode, where multiple logical [`MuxStream`] share
+//! the same io.
+//!
+//! [`DatagramHandle`] is a handle of [`DatagramTask`], the datagram flavor of [`IoTask`], where
+//! each operation is a whole datagram with the peer address.
+//!
+//! # Backpressure
+//!
+//! By default, writes is queued without limit. Use [`Builder`] to limit the write queue by the
+//! total queued bytes or number of queued writes.
+//!
+//! Writing is still non-blocking, instead, caller should wait for
+//! [`IoHandle::write_ready`] or [`IoPoll::poll_write_ready`] before writing. The write queue is
+//! ready when it is below both limit, so a single write may exceed the bytes limit.
+//!
+//! # Timeouts
+//!
+//! By default, [`IoTask`] have no timeout. Use [`Builder`] to configure:
+//!
+//! - read idle timeout, elapsed when no data is read from the io for the duration,
+//! - write stall timeout, elapsed when pending writes does not progress for the duration,
+//! - lifetime timeout, elapsed when the duration since the task first polled is reached.
+//!
+//! When a timeout elapsed, all pending operations returns [`TimedOut`][std::io::ErrorKind::TimedOut]
+//! error and the task is terminated. Timeouts requires the `tokio` feature, and the task to run in
+//! tokio runtime with time driver enabled.
+//!
+//! # Runtime
+//!
+//! Other than timeouts, [`IoTask`] does not depends on any runtime. The task and its handles
+//! communicate using crate internal channels, so the task can be spawned in any executor.
 
 #![allow(missing_debug_implementations, missing_docs, reason = "wip")]
 
 mod task;
+mod budget;
+mod channel;
+mod datagram;
+mod frame;
+mod builder;
 mod handle;
+mod mux;
 mod poll;
+mod stats;
+mod timeout;
+
+use budget::{Budget, Waiter};
 
-pub(crate) use task::{TaskTxMessage, TaskReadMessage, TaskSyncMessage};
+pub(crate) use task::{TaskTxMessage, TaskReadMessage};
 
 pub use task::IoTask;
-pub use handle::{IoHandle, Read, Sync};
+pub use builder::Builder;
+pub use handle::{Detach, IoHandle, Read, ReadFrame, SendTo, Shutdown, Sync, WriteAcked};
+pub use mux::{Accept, MuxHandle, MuxStream};
 pub use poll::IoPoll;
+pub use datagram::{DatagramHandle, DatagramTask, RecvFrom};
+pub use stats::{Observer, Stats, TaskState};
 
@@ -0,0 +1,557 @@
+use bytes::{Bytes, BytesMut};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Poll, ready},
+};
+
+use super::{
+    Budget, Builder, IoTask, Read, Shutdown, Sync, TaskReadMessage, TaskTxMessage, WriteAcked,
+    channel::oneshot::{Receiver, Sender, channel},
+    frame::TaskBuffer,
+    stats::{self, SharedStats, Stats},
+    task::TaskTx,
+};
+use crate::{
+    codec::{Decoder, Encoder},
+    io::{AsyncIoRead, AsyncIoWrite, BufCursor},
+};
+
+type HandleTx = Sender<TaskReadMessage>;
+pub(crate) type AcceptTx = Sender<io::Result<Option<u64>>>;
+type AcceptRx = Receiver<io::Result<Option<u64>>>;
+
+// ===== Codec =====
+
+/// Type erased multiplexing codec.
+pub(crate) trait MuxCodec: Send {
+    fn poll_decode(
+        &mut self,
+        buffer: &mut BytesMut,
+        eof: bool,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<Option<(u64, BytesMut)>>>;
+
+    fn encode(&mut self, id: u64, payload: Bytes, dst: &mut BytesMut) -> io::Result<()>;
+}
+
+impl<C> MuxCodec for C
+where
+    C: Decoder<Item = (u64, BytesMut)> + Encoder<(u64, Bytes)> + Send,
+{
+    fn poll_decode(
+        &mut self,
+        buffer: &mut BytesMut,
+        eof: bool,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<Option<(u64, BytesMut)>>> {
+        Decoder::poll_decode(self, &mut BufCursor::new(TaskBuffer { buffer, eof }), cx)
+    }
+
+    fn encode(&mut self, id: u64, payload: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        Encoder::encode(self, (id, payload), dst)
+    }
+}
+
+// ===== Task State =====
+
+/// Multiplexing state in [`IoTask`].
+pub(crate) struct Mux {
+    codec: Box<dyn MuxCodec>,
+    streams: HashMap<u64, Stream>,
+    /// Streams opened by the peer which is not yet accepted.
+    incoming: VecDeque<u64>,
+    /// Maximum buffered frames of each stream.
+    max_buffered_frames: usize,
+    acceptors: VecDeque<AcceptTx>,
+    write_buf: BytesMut,
+}
+
+#[derive(Default)]
+struct Stream {
+    frames: VecDeque<BytesMut>,
+    readers: VecDeque<HandleTx>,
+}
+
+impl Mux {
+    pub(crate) fn new(codec: Box<dyn MuxCodec>, max_buffered_frames: usize) -> Self {
+        Self {
+            codec,
+            streams: HashMap::new(),
+            incoming: VecDeque::new(),
+            max_buffered_frames,
+            acceptors: VecDeque::new(),
+            write_buf: BytesMut::new(),
+        }
+    }
+
+    /// Register locally opened stream.
+    pub(crate) fn open(&mut self, id: u64) {
+        self.streams.entry(id).or_default();
+        self.incoming.retain(|e| *e != id);
+    }
+
+    pub(crate) fn close(&mut self, id: u64) {
+        self.streams.remove(&id);
+        self.incoming.retain(|e| *e != id);
+    }
+
+    pub(crate) fn read(&mut self, id: u64, tx: HandleTx) {
+        self.streams.entry(id).or_default().readers.push_back(tx);
+    }
+
+    pub(crate) fn accept(&mut self, tx: AcceptTx) {
+        self.acceptors.push_back(tx);
+    }
+
+    /// Encode a frame of the stream.
+    pub(crate) fn encode(&mut self, id: u64, payload: Bytes) -> io::Result<Bytes> {
+        self.codec.encode(id, payload, &mut self.write_buf)?;
+        Ok(self.write_buf.split().freeze())
+    }
+
+    /// Returns the number of pending reads and accepts.
+    pub(crate) fn queued_reads(&self) -> usize {
+        self.acceptors.len() + self.streams.values().map(|s| s.readers.len()).sum::<usize>()
+    }
+
+    /// Returns `true` if there is pending read or accept.
+    fn has_demand(&self) -> bool {
+        !self.acceptors.is_empty() || self.streams.values().any(|s| !s.readers.is_empty())
+    }
+
+    /// Returns `true` if buffered frames of any stream reached the limit.
+    fn is_full(&self) -> bool {
+        self.streams.values().any(|s| s.frames.len() >= self.max_buffered_frames)
+    }
+
+    /// Decode and route frames from the buffer as long as there is pending read or accept, and
+    /// no stream is full.
+    ///
+    /// Returns pending if more data is required.
+    pub(crate) fn poll_route(
+        &mut self,
+        buffer: &mut BytesMut,
+        eof: bool,
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            self.deliver();
+            if !self.has_demand() || self.is_full() {
+                return Poll::Ready(Ok(()));
+            }
+            match ready!(self.codec.poll_decode(buffer, eof, cx)?) {
+                Some((id, payload)) => self.route(id, payload),
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+
+    fn route(&mut self, id: u64, payload: BytesMut) {
+        let stream = self.streams.entry(id).or_insert_with(|| {
+            self.incoming.push_back(id);
+            Stream::default()
+        });
+        stream.frames.push_back(payload);
+    }
+
+    /// Send queued frames to pending reads and incoming streams to pending accepts.
+    fn deliver(&mut self) {
+        for stream in self.streams.values_mut() {
+            stream.readers.retain(|tx| !tx.is_closed());
+            while !stream.frames.is_empty() {
+                let Some(tx) = stream.readers.pop_front() else {
+                    break;
+                };
+                let frame = stream.frames.pop_front().unwrap();
+                // the read is cancelled, keep the frame for the next read
+                if let Err(TaskReadMessage::Data(frame)) = tx.send(TaskReadMessage::Data(frame)) {
+                    stream.frames.push_front(frame);
+                }
+            }
+        }
+
+        while !self.incoming.is_empty() {
+            let Some(tx) = self.acceptors.pop_front() else {
+                break;
+            };
+            let id = self.incoming.pop_front().unwrap();
+            if tx.send(Ok(Some(id))).is_err() {
+                self.incoming.push_front(id);
+            }
+        }
+    }
+
+    /// Deliver remaining frames, then all pending reads returns end of stream.
+    pub(crate) fn handle_eof(&mut self) {
+        self.deliver();
+        for stream in self.streams.values_mut() {
+            for tx in stream.readers.drain(..) {
+                let _ = tx.send(TaskReadMessage::Data(BytesMut::new()));
+            }
+        }
+        for tx in self.acceptors.drain(..) {
+            let _ = tx.send(Ok(None));
+        }
+    }
+
+    /// All pending reads and accepts returns error.
+    pub(crate) fn handle_err(&mut self, err: &io::Error) {
+        let err = || io::Error::new(err.kind(), err.to_string());
+        for stream in self.streams.values_mut() {
+            for tx in stream.readers.drain(..) {
+                let _ = tx.send(TaskReadMessage::Err(err()));
+            }
+        }
+        for tx in self.acceptors.drain(..) {
+            let _ = tx.send(Err(err()));
+        }
+    }
+}
+
+// ===== Handle =====
+
+/// A multiplexing [`IoTask`] handle.
+///
+/// In multiplexing mode, each frame read from the io is decoded into a stream id and its payload
+/// by the given codec, then routed to the [`MuxStream`] with the same id. Writes of a
+/// [`MuxStream`] is encoded with its stream id, and share the same write queue with other
+/// streams.
+///
+/// A stream is opened locally with [`open`][MuxHandle::open]. Frame with unknown stream id is
+/// considered as a stream opened by the peer, which can be received with
+/// [`accept`][MuxHandle::accept]. Frames of a stream is buffered in the task until it is read.
+///
+/// The io is only read when there is pending read or accept, frames of other streams is buffered
+/// in the task. Buffered frames of each stream is limited by [`Builder::max_buffered_frames`],
+/// when a stream reached the limit, the io is not read until the stream frames is read, so
+/// frames of a stream that is never read will stall other streams.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use tcio::io::{AsyncIoRead, AsyncIoWrite};
+/// # async fn app<C>(io: impl AsyncIoRead + AsyncIoWrite, codec: C) -> std::io::Result<()>
+/// # where
+/// #     C: tcio::codec::Decoder<Item = (u64, bytes::BytesMut)>
+/// #         + tcio::codec::Encoder<(u64, bytes::Bytes)> + Send + 'static,
+/// # {
+/// use tcio::io_task::MuxHandle;
+///
+/// let (mux, task) = MuxHandle::new(io, codec);
+///
+/// // the task must be spawned to drive the io
+/// # fn spawn<T>(_: T) { }
+/// spawn(task);
+///
+/// let stream = mux.open(1);
+/// stream.write(bytes::Bytes::from_static(b"Hello"));
+/// let reply = stream.read().await?;
+///
+/// while let Some(stream) = mux.accept().await? {
+///     // handle stream opened by peer
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MuxHandle {
+    tx: TaskTx,
+    budget: Arc<Budget>,
+    stats: SharedStats,
+}
+
+impl MuxHandle {
+    pub(crate) fn from_spawned(tx: TaskTx, budget: Arc<Budget>, stats: SharedStats) -> Self {
+        Self { tx, budget, stats }
+    }
+
+    /// Create new [`IoTask`] in multiplexing mode with [`MuxHandle`] as the handle.
+    ///
+    /// The codec decode frames into stream id and its payload, and encode stream id and payload
+    /// into frames.
+    ///
+    /// To create a bounded [`IoTask`], use [`Builder`].
+    #[inline]
+    pub fn new<IO, C>(io: IO, codec: C) -> (MuxHandle, IoTask<IO>)
+    where
+        IO: AsyncIoRead + AsyncIoWrite,
+        C: Decoder<Item = (u64, BytesMut)> + Encoder<(u64, Bytes)> + Send + 'static,
+    {
+        Builder::new().build_mux(io, codec)
+    }
+
+    /// Returns `true` if IO task is already closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// Returns snapshot of the [`IoTask`] statistics.
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        stats::snapshot(&self.stats)
+    }
+
+    /// Open a stream with given id.
+    ///
+    /// If the peer already sent frames with the same id which is not yet accepted, the stream is
+    /// taken by this call instead.
+    pub fn open(&self, id: u64) -> MuxStream {
+        let _ = self.tx.send(TaskTxMessage::MuxOpen { id });
+        MuxStream::new(id, self.clone())
+    }
+
+    /// Wait for a stream opened by the peer.
+    ///
+    /// Returns `None` if the underlying io reached end of stream.
+    pub fn accept(&self) -> Accept {
+        let (tx, rx) = channel();
+        let repr = match self.tx.send(TaskTxMessage::MuxAccept { tx }) {
+            Ok(()) => Ok(rx),
+            Err(_) => Err(Some(io::ErrorKind::ConnectionAborted.into())),
+        };
+        Accept { repr, handle: self.clone() }
+    }
+
+    /// Wait until the write queue is below the limit.
+    ///
+    /// See [module level docs][super#backpressure] for more details.
+    #[inline]
+    pub fn write_ready(&self) -> impl Future<Output = io::Result<()>> {
+        let mut waiter = self.budget.waiter();
+        std::future::poll_fn(move |cx| waiter.poll_ready(cx))
+    }
+
+    /// Wait for all writes of all streams requested before this call to complete.
+    ///
+    /// See [`IoHandle::sync`][super::IoHandle::sync] for more details.
+    #[inline]
+    pub fn sync(&self) -> Sync {
+        let (tx, rx) = channel();
+        Sync::new(self.tx.send(TaskTxMessage::Sync { tx }), rx)
+    }
+
+    /// Shutdown the write side of the underlying io after all pending writes is completed.
+    ///
+    /// See [`IoHandle::shutdown`][super::IoHandle::shutdown] for more details.
+    #[inline]
+    pub fn shutdown(&self) -> Shutdown {
+        let (tx, rx) = channel();
+        Shutdown::new(self.tx.send(TaskTxMessage::Shutdown { tx }), rx)
+    }
+}
+
+/// A logical stream of multiplexing [`IoTask`].
+///
+/// Dropping the stream discard its buffered frames, further frames with the same id is
+/// considered as new stream opened by the peer.
+#[derive(Debug)]
+pub struct MuxStream {
+    id: u64,
+    handle: MuxHandle,
+}
+
+impl MuxStream {
+    fn new(id: u64, handle: MuxHandle) -> Self {
+        Self { id, handle }
+    }
+
+    /// Returns the stream id.
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Read the next frame payload of this stream.
+    ///
+    /// Returns empty bytes if the underlying io reached end of stream.
+    pub fn read(&self) -> Read {
+        let (tx, rx) = channel();
+        Read::new(self.handle.tx.send(TaskTxMessage::MuxRead { id: self.id, tx }), rx)
+    }
+
+    /// Write a frame with given payload to this stream.
+    ///
+    /// See [`IoHandle::write`][super::IoHandle::write] for more details.
+    pub fn write(&self, bytes: Bytes) {
+        let len = bytes.len();
+        self.handle.budget.acquire(len);
+        let msg = TaskTxMessage::MuxWrite { id: self.id, bytes, ack: None };
+        if self.handle.tx.send(msg).is_err() {
+            self.handle.budget.release(len);
+        }
+    }
+
+    /// Write a frame with given payload to this stream, and wait until the frame is fully written.
+    ///
+    /// See [`IoHandle::write_acked`][super::IoHandle::write_acked] for more details.
+    pub fn write_acked(&self, bytes: Bytes) -> WriteAcked {
+        let len = bytes.len();
+        let (tx, rx) = channel();
+        self.handle.budget.acquire(len);
+        let msg = TaskTxMessage::MuxWrite { id: self.id, bytes, ack: Some(tx) };
+        let sent = self.handle.tx.send(msg);
+        if sent.is_err() {
+            self.handle.budget.release(len);
+        }
+        WriteAcked::new(sent, rx)
+    }
+
+    /// Returns reference to the [`MuxHandle`].
+    #[inline]
+    pub fn handle(&self) -> &MuxHandle {
+        &self.handle
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        let _ = self.handle.tx.send(TaskTxMessage::MuxClose { id: self.id });
+    }
+}
+
+/// Future returned from [`accept`][MuxHandle::accept].
+///
+/// This future is cancel safe as long as the stream is not yet accepted.
+pub struct Accept {
+    repr: Result<AcceptRx, Option<io::Error>>,
+    handle: MuxHandle,
+}
+
+impl Future for Accept {
+    type Output = io::Result<Option<MuxStream>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        let result = match &mut self.repr {
+            Ok(rx) => match ready!(Pin::new(rx).poll(cx)) {
+                Ok(result) => result,
+                Err(_) => Err(io::ErrorKind::ConnectionAborted.into()),
+            },
+            Err(err) => Err(err.take().unwrap()),
+        };
+
+        Poll::Ready(result.map(|id| id.map(|id| MuxStream::new(id, self.handle.clone()))))
+    }
+}
+
+impl std::fmt::Debug for Accept {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Accept").finish_non_exhaustive()
+    }
+}
+
+#[test]
+fn test_mux() {
+    use bytes::{Buf, BufMut};
+    use std::pin::pin;
+
+    use crate::io::{AsyncBufRead, mock};
+
+    /// One byte stream id and one byte payload length.
+    struct Codec;
+
+    impl Decoder for Codec {
+        type Item = (u64, BytesMut);
+
+        fn poll_decode<B: AsyncBufRead>(
+            &mut self,
+            cursor: &mut BufCursor<B>,
+            cx: &mut std::task::Context,
+        ) -> Poll<io::Result<Option<Self::Item>>> {
+            if ready!(cursor.poll_is_eof(cx)?) {
+                return Poll::Ready(Ok(None));
+            }
+            let head = ready!(cursor.poll_peek(2, cx)?);
+            let (id, len) = (head[0] as u64, head[1] as usize);
+            let mut frame = ready!(cursor.poll_take_mut(2 + len, cx)?);
+            frame.advance(2);
+            Poll::Ready(Ok(Some((id, frame))))
+        }
+    }
+
+    impl Encoder<(u64, Bytes)> for Codec {
+        fn encode(&mut self, (id, payload): (u64, Bytes), dst: &mut BytesMut) -> io::Result<()> {
+            dst.put_u8(id as u8);
+            dst.put_u8(payload.len() as u8);
+            dst.put(payload);
+            Ok(())
+        }
+    }
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .read(b"\x01\x03Foo\x02\x03Bar\x01\x03Baz")
+        .write(b"\x02\x02Hi")
+        .build();
+    let (mux, task) = MuxHandle::new(io, Codec);
+    let mut task = pin!(task);
+
+    let one = mux.open(1);
+    let mut foo = pin!(one.read());
+    let mut accept = pin!(mux.accept());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Foo"));
+    let Poll::Ready(Ok(Some(two))) = accept.as_mut().poll(&mut cx) else {
+        panic!("stream is not accepted")
+    };
+    assert_eq!(two.id(), 2);
+
+    // frames is routed to its stream
+    let mut baz = pin!(one.read());
+    let mut bar = pin!(two.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(baz.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Baz"));
+    assert!(matches!(bar.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Bar"));
+
+    let mut hi = pin!(two.write_acked(Bytes::from_static(b"Hi")));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(hi.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+
+    // end of stream
+    let mut eof = pin!(one.read());
+    let mut accept = pin!(mux.accept());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(eof.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b.is_empty()));
+    assert!(matches!(accept.as_mut().poll(&mut cx), Poll::Ready(Ok(None))));
+
+    // io is not read when buffered frames of a stream reached the limit
+    let io = mock::Builder::new().read(b"\x02\x01A\x01\x03Foo").build();
+    let (mux, task) = Builder::new().max_buffered_frames(1).build_mux(io, Codec);
+    let mut task = pin!(task);
+
+    let one = mux.open(1);
+    let mut foo = pin!(one.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(foo.as_mut().poll(&mut cx).is_pending());
+
+    let mut accept = pin!(mux.accept());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    let Poll::Ready(Ok(Some(two))) = accept.as_mut().poll(&mut cx) else {
+        panic!("stream is not accepted")
+    };
+    // accepted stream is still full
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(foo.as_mut().poll(&mut cx).is_pending());
+
+    let mut a = pin!(two.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(a.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "A"));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Foo"));
+
+    // reads is completed in order, even when awaited out of order
+    let io = mock::Builder::new().read(b"\x01\x03Foo\x01\x03Bar").build();
+    let (mux, task) = MuxHandle::new(io, Codec);
+    let mut task = pin!(task);
+
+    let one = mux.open(1);
+    drop(one.read());
+    let mut foo = pin!(one.read());
+    let mut bar = pin!(one.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(bar.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Bar"));
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Foo"));
+}
@@ -2,15 +2,17 @@ use bytes::{Bytes, BytesMut};
 use std::{
     io,
     pin::Pin,
+    sync::Arc,
     task::{Poll, ready},
 };
-use tokio::sync::oneshot::channel;
 
 use crate::io::{AsyncIoRead, AsyncIoWrite};
 
 use super::{
-    IoTask, TaskReadMessage, TaskSyncMessage, TaskTxMessage,
-    task::{TaskReadRx, TaskSyncRx, TaskTx},
+    Budget, Builder, IoTask, TaskReadMessage, TaskTxMessage, Waiter,
+    channel::oneshot::channel,
+    stats::{self, SharedStats, Stats},
+    task::{TaskReadRx, TaskResultRx, TaskResultTx, TaskTx},
 };
 
 macro_rules! poll_err {
@@ -24,36 +26,54 @@ macro_rules! poll_err {
 /// This handle is "statefull" in a sense that all operations requires mutable reference, and
 /// provide poll based operation.
 ///
-/// Note that all `poll_*` operation is not cancel safe. If `poll_read` called returns pending, and
-/// then user call `poll_sync`, it will returns an error.
+/// Each kind of operation have its own state, so a pending operation can be resumed after polling
+/// other kind of operation. For example, if `poll_read` returns pending, user can call
+/// `poll_sync`, and the next `poll_read` continue the pending read.
+///
+/// Reading is cancel safe, if `poll_read_exact` is called with different length while a read is
+/// pending, the already read data is not lost and returned by the next read.
 ///
 /// Note that [`Clone`] implementation of this handle does not clone the state, meaning that if the
 /// original handle is in pending read, the cloned handle does not have any pending operation.
 ///
 /// See [crate level docs][super] for more details.
 pub struct IoPoll {
-    ops: Option<Operation>,
+    read: Option<(Option<usize>, TaskReadRx)>,
+    /// Data of a read which is cancelled by different length.
+    leftover: BytesMut,
+    sync: Option<TaskResultRx>,
+    shutdown: Option<TaskResultRx>,
+    detach: Option<TaskResultRx>,
+    write_ready: Waiter,
     tx: TaskTx,
-}
-
-enum Operation {
-    Read(TaskReadRx),
-    Sync(TaskSyncRx),
+    budget: Arc<Budget>,
+    stats: SharedStats,
 }
 
 impl IoPoll {
-    pub(crate) fn from_spawned(tx: TaskTx) -> Self {
-        Self { ops: None, tx }
+    pub(crate) fn from_spawned(tx: TaskTx, budget: Arc<Budget>, stats: SharedStats) -> Self {
+        Self {
+            read: None,
+            leftover: BytesMut::new(),
+            sync: None,
+            shutdown: None,
+            detach: None,
+            write_ready: budget.waiter(),
+            tx,
+            budget,
+            stats,
+        }
     }
 
     /// Create new [`IoTask`] with [`IoPoll`] as the handle.
+    ///
+    /// To create a bounded [`IoTask`], use [`Builder`].
     #[inline]
     pub fn new<IO>(io: IO) -> (IoPoll, IoTask<IO>)
     where
         IO: AsyncIoRead + AsyncIoWrite,
     {
-        let (tx, task) = IoTask::new(io);
-        (Self { ops: None, tx, }, task)
+        Builder::new().build_poll(io)
     }
 
     // ===== Public =====
@@ -64,101 +84,169 @@ impl IoPoll {
         self.tx.is_closed()
     }
 
+    /// Returns snapshot of the [`IoTask`] statistics.
+    #[inline]
+    pub fn stats(&self) -> Stats {
+        stats::snapshot(&self.stats)
+    }
+
     /// Poll for read bytes from underlying IO.
+    ///
+    /// Returns empty bytes if the underlying IO reached end of stream.
     #[inline]
     pub fn poll_read(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<BytesMut>> {
         self.poll_read_inner(None, cx)
     }
 
     /// Poll for read exact size bytes from underlying IO.
+    ///
+    /// Returns [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] error if the underlying IO reached
+    /// end of stream before `len` bytes is read.
     #[inline]
     pub fn poll_read_exact(&mut self, len: usize, cx: &mut std::task::Context) -> Poll<io::Result<BytesMut>> {
         self.poll_read_inner(Some(len), cx)
     }
 
     fn poll_read_inner(&mut self, cap: Option<usize>, cx: &mut std::task::Context) -> Poll<io::Result<BytesMut>> {
-        use Operation::*;
-
-        match &mut self.ops {
-            Some(Read(rx)) => {
-                let result = match ready!(Pin::new(rx).poll(cx)) {
-                    Ok(TaskReadMessage::Data(ok)) => Ok(ok),
-                    Ok(TaskReadMessage::Err(err)) => Err(err),
-                    Err(_) => return poll_err!("`IoTask` is already closed")
-                };
-                self.ops.take();
-                Poll::Ready(result)
-            },
-            Some(Sync(_)) => poll_err!("`IoPoll::poll_sync` is pending"),
+        if let Some((pending, rx)) = &mut self.read
+            && *pending != cap
+        {
+            // different length cancels the pending read, the already read data is kept
+            let poll = Pin::new(rx).poll(cx);
+            self.read.take();
+            match poll {
+                Poll::Ready(Ok(TaskReadMessage::Data(data))) => self.leftover.unsplit(data),
+                Poll::Ready(Ok(TaskReadMessage::Err(err))) => return Poll::Ready(Err(err)),
+                Poll::Ready(Err(_)) => return poll_err!("`IoTask` is already closed"),
+                // dropping the receiver returns the data to the task
+                Poll::Pending => {}
+            }
+        }
+
+        if self.read.is_none() && !self.leftover.is_empty() {
+            match cap {
+                None => return Poll::Ready(Ok(self.leftover.split())),
+                Some(len) if self.leftover.len() >= len => {
+                    return Poll::Ready(Ok(self.leftover.split_to(len)));
+                }
+                Some(_) => {}
+            }
+        }
+
+        let rx = match &mut self.read {
+            Some((_, rx)) => rx,
             None => {
                 let (tx, rx) = channel();
-                if self.tx.send(TaskTxMessage::Read { cap, tx }).is_err() {
+                let remaining = cap.map(|len| len - self.leftover.len());
+                if self.tx.send(TaskTxMessage::Read { cap: remaining, tx }).is_err() {
                     return poll_err!("`IoTask` is already closed");
                 }
-                self.ops = Some(Read(rx));
-                self.poll_read_inner(cap, cx)
+                &mut self.read.insert((cap, rx)).1
             },
-        }
+        };
+        let result = match ready!(Pin::new(rx).poll(cx)) {
+            Ok(TaskReadMessage::Data(data)) => {
+                let mut leftover = self.leftover.split();
+                leftover.unsplit(data);
+                Ok(leftover)
+            },
+            Ok(TaskReadMessage::Err(err)) => Err(err),
+            Err(_) => return poll_err!("`IoTask` is already closed")
+        };
+        self.read.take();
+        Poll::Ready(result)
     }
 
     /// Write bytes to the underlying io.
+    ///
+    /// This does not wait for the write queue limit, use
+    /// [`poll_write_ready`][IoPoll::poll_write_ready] before writing to respect the limit.
     #[inline]
     pub fn write(&self, bytes: Bytes) {
-        let _ = self.tx.send(TaskTxMessage::Write { bytes });
+        let len = bytes.len();
+        self.budget.acquire(len);
+        if self.tx.send(TaskTxMessage::Write { bytes, ack: None }).is_err() {
+            self.budget.release(len);
+        }
     }
 
-    /// Poll for all write operations completions.
+    /// Poll until the write queue is below the limit.
+    ///
+    /// This operation does not conflict with pending read or sync.
+    ///
+    /// See [module level docs][super#backpressure] for more details.
+    #[inline]
+    pub fn poll_write_ready(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        self.write_ready.poll_ready(cx)
+    }
+
+    /// Poll for all writes requested before the first call to complete.
+    ///
+    /// See [`IoHandle::sync`][super::IoHandle::sync] for more details.
     pub fn poll_sync(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
-        use Operation::*;
-
-        match &mut self.ops {
-            Some(Read(_)) => poll_err!("`IoPoll::poll_read` is pending"),
-            Some(Sync(rx)) => {
-                let result = match ready!(Pin::new(rx).poll(cx)) {
-                    Ok(TaskSyncMessage::Pending) => return Poll::Pending,
-                    Ok(TaskSyncMessage::Ok) => Ok(()),
-                    Ok(TaskSyncMessage::Err(err)) => Err(err),
-                    Err(_) => return poll_err!("`IoTask` is already closed")
-                };
-                self.ops.take();
-                Poll::Ready(result)
-            },
-            None => {
-                let (tx, rx) = channel();
-                if self.tx.send(TaskTxMessage::Sync { tx }).is_err() {
-                    return poll_err!("`IoTask` is already closed");
-                }
-                self.ops = Some(Sync(rx));
-                self.poll_sync(cx)
-            },
-        }
+        poll_result(&mut self.sync, &self.tx, |tx| TaskTxMessage::Sync { tx }, cx)
+    }
+
+    /// Poll for write side shutdown after all pending writes is completed.
+    ///
+    /// See [`IoHandle::shutdown`][super::IoHandle::shutdown] for more details.
+    pub fn poll_shutdown(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        poll_result(&mut self.shutdown, &self.tx, |tx| TaskTxMessage::Shutdown { tx }, cx)
+    }
+
+    /// Poll for stopping the [`IoTask`] after all pending writes is completed.
+    ///
+    /// See [`IoHandle::detach`][super::IoHandle::detach] for more details.
+    pub fn poll_detach(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        poll_result(&mut self.detach, &self.tx, |tx| TaskTxMessage::Detach { tx }, cx)
     }
 
     /// Convert into shared handle [`IoHandle`][super::IoHandle].
     #[inline]
     pub fn into_handle(self) -> super::IoHandle {
-        super::IoHandle::from_spawned(self.tx)
+        super::IoHandle::from_spawned(self.tx, self.budget, self.stats)
     }
 }
 
 impl Clone for IoPoll {
     fn clone(&self) -> Self {
-        Self {
-            ops: None,
-            tx: self.tx.clone(),
-        }
+        Self::from_spawned(self.tx.clone(), self.budget.clone(), self.stats.clone())
     }
 }
 
 impl std::fmt::Debug for IoPoll {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("IoPoll")
-            .field("state", match &self.ops {
-                Some(Operation::Read(_)) => &"Reading",
-                Some(Operation::Sync(_)) => &"Syncing",
-                None => &"Idle",
-            })
+            .field("reading", &self.read.is_some())
+            .field("syncing", &self.sync.is_some())
+            .field("shutting_down", &self.shutdown.is_some())
+            .field("detaching", &self.detach.is_some())
             .finish()
     }
 }
 
+/// Poll operation which result is a single [`io::Result`], the request is send when `state` is
+/// empty.
+fn poll_result(
+    state: &mut Option<TaskResultRx>,
+    task: &TaskTx,
+    msg: impl FnOnce(TaskResultTx) -> TaskTxMessage,
+    cx: &mut std::task::Context,
+) -> Poll<io::Result<()>> {
+    let rx = match state {
+        Some(rx) => rx,
+        None => {
+            let (tx, rx) = channel();
+            if task.send(msg(tx)).is_err() {
+                return poll_err!("`IoTask` is already closed");
+            }
+            state.insert(rx)
+        }
+    };
+    let result = match ready!(Pin::new(rx).poll(cx)) {
+        Ok(result) => result,
+        Err(_) => return poll_err!("`IoTask` is already closed")
+    };
+    state.take();
+    Poll::Ready(result)
+}
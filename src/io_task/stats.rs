@@ -0,0 +1,151 @@
+use std::{
+    io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// Shared [`Stats`] between [`IoTask`][super::IoTask] and its handles.
+pub(crate) type SharedStats = Arc<Mutex<Stats>>;
+
+/// Returns a copy of the shared stats.
+pub(crate) fn snapshot(stats: &SharedStats) -> Stats {
+    stats.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+/// Snapshot of [`IoTask`][super::IoTask] statistics.
+///
+/// The statistics is updated by the task each time it is polled.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub(crate) bytes_read: u64,
+    pub(crate) bytes_written: u64,
+    pub(crate) queued_reads: usize,
+    pub(crate) queued_writes: usize,
+    pub(crate) queued_write_bytes: usize,
+    pub(crate) last_error: Option<Arc<io::Error>>,
+    pub(crate) read_eof: bool,
+    pub(crate) write_closed: bool,
+    pub(crate) state: TaskState,
+}
+
+impl Stats {
+    /// Returns total bytes read from the io.
+    #[inline]
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns total bytes written to the io.
+    #[inline]
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Returns the number of pending reads.
+    #[inline]
+    pub fn queued_reads(&self) -> usize {
+        self.queued_reads
+    }
+
+    /// Returns the number of pending writes.
+    #[inline]
+    pub fn queued_writes(&self) -> usize {
+        self.queued_writes
+    }
+
+    /// Returns the total bytes of pending writes.
+    #[inline]
+    pub fn queued_write_bytes(&self) -> usize {
+        self.queued_write_bytes
+    }
+
+    /// Returns the last io error occured in the task.
+    #[inline]
+    pub fn last_error(&self) -> Option<&io::Error> {
+        self.last_error.as_deref()
+    }
+
+    /// Returns `true` if the read side reached end of stream.
+    #[inline]
+    pub fn is_read_eof(&self) -> bool {
+        self.read_eof
+    }
+
+    /// Returns `true` if the write side is shutdown.
+    #[inline]
+    pub fn is_write_closed(&self) -> bool {
+        self.write_closed
+    }
+
+    /// Returns the task state.
+    #[inline]
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+}
+
+/// [`IoTask`][super::IoTask] state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TaskState {
+    /// The task is processing operations.
+    #[default]
+    Running,
+    /// Detach is requested, the task is completing pending writes.
+    Detaching,
+    /// A timeout elapsed.
+    TimedOut,
+    /// The task is completed or dropped.
+    Terminated,
+}
+
+/// Observer of [`IoTask`][super::IoTask] events.
+///
+/// All methods is called inside the task, so it should not block. The default implementation
+/// does nothing.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use tcio::io_task::{Builder, Observer};
+///
+/// #[derive(Default)]
+/// struct Throughput {
+///     read: AtomicU64,
+///     written: AtomicU64,
+/// }
+///
+/// impl Observer for Throughput {
+///     fn on_read(&self, len: usize) {
+///         self.read.fetch_add(len as u64, Ordering::Relaxed);
+///     }
+///
+///     fn on_write(&self, len: usize) {
+///         self.written.fetch_add(len as u64, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let mut builder = Builder::new();
+/// builder.observer(Throughput::default());
+/// ```
+pub trait Observer: Send + Sync + 'static {
+    /// Called when `len` bytes is read from the io.
+    fn on_read(&self, len: usize) {
+        let _ = len;
+    }
+
+    /// Called when `len` bytes is written to the io.
+    fn on_write(&self, len: usize) {
+        let _ = len;
+    }
+
+    /// Called when an io error occured.
+    fn on_error(&self, err: &io::Error) {
+        let _ = err;
+    }
+
+    /// Called when the task is completed or dropped.
+    fn on_terminate(&self, stats: &Stats) {
+        let _ = stats;
+    }
+}
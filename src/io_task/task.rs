@@ -1,32 +1,96 @@
-use bytes::{Bytes, BytesMut};
-use std::{collections::VecDeque, io, mem::take, pin::Pin, task::Poll};
-use tokio::sync::{
-    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
-    oneshot::{Receiver, Sender},
+use bytes::{Buf, Bytes, BytesMut};
+use std::{
+    collections::VecDeque,
+    io,
+    mem::take,
+    pin::Pin,
+    sync::{Arc, PoisonError},
+    task::{Poll, ready},
 };
 
+use super::{
+    Budget,
+    channel::{
+        mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+        oneshot::{Receiver, Sender},
+    },
+    frame::FrameRead,
+    mux::{AcceptTx, Mux},
+    stats::{Observer, SharedStats, Stats, TaskState},
+    timeout::{Timeouts, Timers, timed_out},
+};
 use crate::io::{AsyncIoRead, AsyncIoWrite};
 
 pub(crate) type TaskTx = UnboundedSender<TaskTxMessage>;
 pub(crate) type TaskRx = UnboundedReceiver<TaskTxMessage>;
 pub(crate) type TaskReadRx = Receiver<TaskReadMessage>;
-pub(crate) type TaskSyncTx = Sender<TaskSyncMessage>;
-pub(crate) type TaskSyncRx = Receiver<TaskSyncMessage>;
+pub(crate) type TaskResultTx = Sender<io::Result<()>>;
+pub(crate) type TaskResultRx = Receiver<io::Result<()>>;
 type HandleTx = Sender<TaskReadMessage>;
 
-struct ReadTask {
-    cap: Option<usize>,
-    tx: HandleTx,
+/// Maximum buffers in a single vectored write, the common `IOV_MAX` value.
+const MAX_IOV: usize = 1024;
+
+enum ReadTask {
+    /// Read with optional exact size.
+    Read {
+        cap: Option<usize>,
+        tx: HandleTx,
+    },
+    /// Read until delimiter.
+    Until {
+        delim: u8,
+        /// Length of the buffer which already searched for delimiter.
+        scanned: usize,
+        tx: HandleTx,
+    },
+    /// Read a frame with type erased decoder.
+    Frame {
+        frame: Box<dyn FrameRead>,
+        /// Write sequence of the request, if the frame is the response of a call.
+        call: Option<u64>,
+    },
 }
 
 impl ReadTask {
-    fn send(self, data: TaskReadMessage) {
-        let _ = self.tx.send(data);
+    fn send_err(self, err: io::Error) {
+        match self {
+            ReadTask::Read { tx, .. } | ReadTask::Until { tx, .. } => {
+                let _ = tx.send(TaskReadMessage::Err(err));
+            }
+            ReadTask::Frame { mut frame, .. } => frame.send_err(err),
+        }
+    }
+
+    /// Returns `true` if the read future is dropped.
+    fn is_cancelled(&self) -> bool {
+        match self {
+            ReadTask::Read { tx, .. } | ReadTask::Until { tx, .. } => tx.is_closed(),
+            ReadTask::Frame { frame, .. } => frame.is_cancelled(),
+        }
     }
 }
 
-struct WriteTask {
-    bytes: Bytes,
+enum WriteTask {
+    Write {
+        bytes: Bytes,
+        /// Length accounted in the budget.
+        len: usize,
+        ack: Option<TaskResultTx>,
+    },
+    Shutdown {
+        tx: TaskResultTx,
+    },
+    Detach {
+        tx: TaskResultTx,
+    },
+    /// A write which is failed before written, such as encoding error.
+    Error {
+        err: io::Error,
+        /// Length accounted in the budget.
+        len: usize,
+        ack: Option<TaskResultTx>,
+    },
 }
 
 pub enum TaskTxMessage {
@@ -37,13 +101,74 @@ pub enum TaskTxMessage {
         cap: Option<usize>,
         tx: HandleTx,
     },
+    /// Read from io until the delimiter, including the delimiter.
+    ///
+    /// When ready, [`Data`][TaskReadMessage::Data] message will be send to `tx`.
+    ReadUntil {
+        delim: u8,
+        tx: HandleTx,
+    },
+    /// Read a frame decoded in the task.
+    ///
+    /// When ready, the result is send by the frame task itself.
+    ReadFrame {
+        task: Box<dyn FrameRead>,
+    },
     /// Write given bytes to io.
+    ///
+    /// If `ack` is present, the write result will be send to `ack` instead of reported on sync.
     Write {
         bytes: Bytes,
+        ack: Option<TaskResultTx>,
+    },
+    /// Write given bytes to io, and read a frame decoded in the task as the response.
+    ///
+    /// Both is queued at once, so the response is paired with the request.
+    Call {
+        bytes: Bytes,
+        task: Box<dyn FrameRead>,
     },
-    /// Request `writing` status, a [`TaskSyncMessage`] will be send immediately.
+    /// Wait for all writes requested before this message.
+    ///
+    /// When completed, the first error of those writes that is not yet reported will be send to
+    /// `tx`.
     Sync {
-        tx: TaskSyncTx,
+        tx: TaskResultTx,
+    },
+    /// Shutdown the write side after all pending writes.
+    ///
+    /// When completed, the result will be send to `tx`.
+    Shutdown {
+        tx: TaskResultTx,
+    },
+    /// Stop the task after all pending writes, messages after this is not processed.
+    ///
+    /// When completed, the result will be send to `tx`.
+    Detach {
+        tx: TaskResultTx,
+    },
+    /// Register locally opened stream in multiplexing mode.
+    MuxOpen {
+        id: u64,
+    },
+    /// Read the next frame of a stream in multiplexing mode.
+    MuxRead {
+        id: u64,
+        tx: HandleTx,
+    },
+    /// Encode and write a frame of a stream in multiplexing mode.
+    MuxWrite {
+        id: u64,
+        bytes: Bytes,
+        ack: Option<TaskResultTx>,
+    },
+    /// Wait for a stream opened by the peer in multiplexing mode.
+    MuxAccept {
+        tx: AcceptTx,
+    },
+    /// Discard a stream in multiplexing mode.
+    MuxClose {
+        id: u64,
     },
 }
 
@@ -55,28 +180,52 @@ pub enum TaskReadMessage {
     Err(io::Error),
 }
 
-/// Status for [`Sync`][TaskTxMessage::Sync] request.
-pub enum TaskSyncMessage {
-    /// There is still write operation pending.
-    Pending,
-    /// All write operations completed successfully.
-    Ok,
-    /// An error occured on the most recent write operation.
-    Err(io::Error),
-}
-
 // ===== IoTask =====
 
 /// A future to drive the concurent io operation.
 ///
+/// The task completes when all handles is dropped and all pending writes is completed, when both
+/// the read side reached end of stream and the write side is shutdown, or when
+/// [`IoHandle::detach`][super::IoHandle::detach] is requested.
+///
+/// When completed, the task returns the underlying io and any buffered bytes that is not read.
+///
 /// See [crate level docs][super] for more details.
 pub struct IoTask<IO> {
     rx: TaskRx,
-    io: IO,
+    /// `None` after the task completed.
+    io: Option<IO>,
     buffer: BytesMut,
     read_queue: VecDeque<ReadTask>,
     write_queue: VecDeque<WriteTask>,
-    write_err: Option<io::Error>,
+    /// Errors of unacknowledged writes with its write sequence.
+    write_errs: VecDeque<(u64, io::Error)>,
+    /// Pending sync requests with the write sequence to wait for.
+    sync_queue: VecDeque<(u64, TaskResultTx)>,
+    /// Number of requested writes.
+    write_seq: u64,
+    /// Number of completed writes.
+    written_seq: u64,
+    budget: Arc<Budget>,
+    /// All handles is dropped.
+    rx_closed: bool,
+    /// Read side reached end of stream.
+    read_eof: bool,
+    /// Write side is shutdown.
+    write_closed: bool,
+    /// Detach is requested, no more message is processed.
+    detaching: bool,
+    /// Detach is completed.
+    detached: bool,
+    timers: Timers,
+    /// A timeout elapsed.
+    timed_out: bool,
+    /// Task local statistics, published to `shared_stats` on each poll.
+    stats: Stats,
+    shared_stats: SharedStats,
+    observer: Option<Arc<dyn Observer>>,
+    /// Multiplexing mode.
+    mux: Option<Mux>,
 }
 
 impl<IO> Unpin for IoTask<IO> {}
@@ -85,183 +234,1088 @@ impl<IO> IoTask<IO>
 where
     IO: AsyncIoRead + AsyncIoWrite,
 {
-    pub(crate) fn new(io: IO) -> (TaskTx, Self) {
+    pub(crate) fn new(
+        io: IO,
+        budget: Arc<Budget>,
+        timeouts: Timeouts,
+        shared_stats: SharedStats,
+        observer: Option<Arc<dyn Observer>>,
+        mux: Option<Mux>,
+    ) -> (TaskTx, Self) {
         let (tx, rx) = unbounded_channel();
         let me = Self {
             rx,
-            io,
+            io: Some(io),
             buffer: BytesMut::with_capacity(0x0400),
             read_queue: VecDeque::new(),
             write_queue: VecDeque::new(),
-            write_err: None,
+            write_errs: VecDeque::new(),
+            sync_queue: VecDeque::new(),
+            write_seq: 0,
+            written_seq: 0,
+            budget,
+            rx_closed: false,
+            read_eof: false,
+            write_closed: false,
+            detaching: false,
+            detached: false,
+            timers: Timers::new(timeouts),
+            timed_out: false,
+            stats: Stats::default(),
+            shared_stats,
+            observer,
+            mux,
         };
         (tx, me)
     }
 
     // ===== Helper =====
 
-    fn terminate(&mut self) {
-        self.write_err = Some(io::ErrorKind::ConnectionAborted.into());
-        for task in take(&mut self.read_queue) {
-            task.send(TaskReadMessage::Err(io::ErrorKind::ConnectionAborted.into()));
+    fn can_terminate(&self) -> bool {
+        self.detached
+            || self.timed_out
+            || self.write_queue.is_empty() && (self.rx_closed || (self.read_eof && self.write_closed))
+    }
+
+    fn on_read(&mut self, len: usize) {
+        self.stats.bytes_read += len as u64;
+        if let Some(observer) = &self.observer {
+            observer.on_read(len);
         }
     }
 
-    fn is_terminating(&self) -> bool {
-        matches!(&self.write_err, Some(err) if err.kind() == io::ErrorKind::ConnectionAborted)
+    fn on_write(&mut self, len: usize) {
+        self.stats.bytes_written += len as u64;
+        if let Some(observer) = &self.observer {
+            observer.on_write(len);
+        }
     }
 
-    fn can_terminate(&self) -> bool {
-        self.is_terminating() && self.write_queue.is_empty()
+    fn on_error(&mut self, err: &io::Error) {
+        self.stats.last_error = Some(Arc::new(io::Error::new(err.kind(), err.to_string())));
+        if let Some(observer) = &self.observer {
+            observer.on_error(err);
+        }
+    }
+
+    /// Release a completed or discarded write.
+    fn release_write(&mut self, len: usize) {
+        self.budget.release(len);
+        self.stats.queued_writes -= 1;
+        self.stats.queued_write_bytes -= len;
     }
 
+
+    /// Send data to the first reader, the data is returned to the buffer if the reader is
+    /// cancelled.
+    ///
+    /// The data must be split from the front of the buffer.
     fn send_reader(&mut self, data: BytesMut) {
-        if let Some(task) = self.read_queue.pop_front() {
-            task.send(TaskReadMessage::Data(data));
+        let task = self.read_queue.pop_front();
+        let Some(ReadTask::Read { tx, .. } | ReadTask::Until { tx, .. }) = task else {
+            return;
+        };
+        if let Err(TaskReadMessage::Data(data)) = tx.send(TaskReadMessage::Data(data)) {
+            self.return_data(data);
         }
     }
 
+    /// Returns read data to the front of the buffer.
+    fn return_data(&mut self, mut data: BytesMut) {
+        data.unsplit(take(&mut self.buffer));
+        self.buffer = data;
+    }
+
+    /// Remove readers which future is dropped.
+    fn remove_cancelled(&mut self) {
+        self.read_queue.retain(|task| !task.is_cancelled());
+    }
+
     fn send_reader_err(&mut self, err: io::Error) {
         if let Some(task) = self.read_queue.pop_front() {
-            task.send(TaskReadMessage::Err(err));
+            task.send_err(err);
         }
     }
 
     // ===== Operations =====
 
     fn poll_message(&mut self, cx: &mut std::task::Context) {
-        if self.is_terminating() {
+        if self.rx_closed || self.detaching {
             return;
         }
 
         let msg = match self.rx.poll_recv(cx) {
             Poll::Ready(Some(msg)) => msg,
             Poll::Ready(None) => {
-                self.terminate();
+                self.rx_closed = true;
+                for task in take(&mut self.read_queue) {
+                    task.send_err(io::ErrorKind::ConnectionAborted.into());
+                }
+                if let Some(mux) = &mut self.mux {
+                    mux.handle_err(&io::ErrorKind::ConnectionAborted.into());
+                }
                 return;
             }
             Poll::Pending => return,
         };
 
         match msg {
-            TaskTxMessage::Read { cap, tx } => self.read_queue.push_back(ReadTask { cap, tx }),
-            TaskTxMessage::Write { bytes } => self.write_queue.push_back(WriteTask { bytes }),
-            TaskTxMessage::Sync { tx } => self.handle_sync(tx),
+            TaskTxMessage::Read { cap, tx } => self.read_queue.push_back(ReadTask::Read { cap, tx }),
+            TaskTxMessage::ReadUntil { delim, tx } => {
+                self.read_queue.push_back(ReadTask::Until { delim, scanned: 0, tx });
+            }
+            TaskTxMessage::ReadFrame { task } => {
+                self.read_queue.push_back(ReadTask::Frame { frame: task, call: None });
+            }
+            TaskTxMessage::Write { bytes, ack } => {
+                let len = bytes.len();
+                self.write_seq += 1;
+                self.stats.queued_writes += 1;
+                self.stats.queued_write_bytes += len;
+                self.write_queue.push_back(WriteTask::Write { bytes, len, ack });
+            }
+            TaskTxMessage::Call { bytes, task } => {
+                let len = bytes.len();
+                self.write_seq += 1;
+                self.stats.queued_writes += 1;
+                self.stats.queued_write_bytes += len;
+                self.write_queue.push_back(WriteTask::Write { bytes, len, ack: None });
+                let call = Some(self.write_seq);
+                self.read_queue.push_back(ReadTask::Frame { frame: task, call });
+            }
+            TaskTxMessage::Sync { tx } => {
+                self.sync_queue.push_back((self.write_seq, tx));
+                self.handle_sync();
+            }
+            TaskTxMessage::Shutdown { tx } => self.write_queue.push_back(WriteTask::Shutdown { tx }),
+            TaskTxMessage::Detach { tx } => {
+                self.detaching = true;
+                self.write_queue.push_back(WriteTask::Detach { tx });
+                return;
+            }
+            TaskTxMessage::MuxWrite { id, bytes, ack } => {
+                let len = bytes.len();
+                self.write_seq += 1;
+                self.stats.queued_writes += 1;
+                self.stats.queued_write_bytes += len;
+                let task = match self.mux.as_mut().map(|mux| mux.encode(id, bytes)) {
+                    Some(Ok(bytes)) => WriteTask::Write { bytes, len, ack },
+                    Some(Err(err)) => WriteTask::Error { err, len, ack },
+                    None => WriteTask::Error { err: not_mux(), len, ack },
+                };
+                self.write_queue.push_back(task);
+            }
+            msg => match (&mut self.mux, msg) {
+                (Some(mux), TaskTxMessage::MuxOpen { id }) => mux.open(id),
+                (Some(mux), TaskTxMessage::MuxRead { id, tx }) => mux.read(id, tx),
+                (Some(mux), TaskTxMessage::MuxAccept { tx }) => mux.accept(tx),
+                (Some(mux), TaskTxMessage::MuxClose { id }) => mux.close(id),
+                (None, TaskTxMessage::MuxRead { tx, .. }) => {
+                    let _ = tx.send(TaskReadMessage::Err(not_mux()));
+                }
+                (None, TaskTxMessage::MuxAccept { tx }) => {
+                    let _ = tx.send(Err(not_mux()));
+                }
+                _ => {}
+            },
         }
 
         self.poll_message(cx)
     }
 
-    fn handle_sync(&mut self, tx: Sender<TaskSyncMessage>) {
-        let _ = tx.send(match (self.write_err.take(), self.write_queue.is_empty()) {
-            (None, true) => TaskSyncMessage::Ok,
-            (None, false) => TaskSyncMessage::Pending,
-            (Some(err), _) => TaskSyncMessage::Err(err),
-        });
+    /// Answer sync requests which all its writes is completed.
+    fn handle_sync(&mut self) {
+        while let Some((seq, _)) = self.sync_queue.front() {
+            if *seq > self.written_seq {
+                return;
+            }
+            let (seq, tx) = self.sync_queue.pop_front().unwrap();
+            let _ = tx.send(self.take_write_err(seq));
+        }
+    }
+
+    /// Take the first error of writes up to `seq`, other errors is discarded.
+    fn take_write_err(&mut self, seq: u64) -> io::Result<()> {
+        let mut result = Ok(());
+        while self.write_errs.front().is_some_and(|(s, _)| *s <= seq) {
+            let (_, err) = self.write_errs.pop_front().unwrap();
+            if result.is_ok() {
+                result = Err(err);
+            }
+        }
+        result
     }
 
     fn poll_read(&mut self, cx: &mut std::task::Context) {
-        if self.is_terminating() {
+        if self.detached {
+            return;
+        }
+
+        if self.mux.is_some() {
+            self.poll_mux_read(cx);
             return;
         }
 
-        self.handle_buffer();
+        self.remove_cancelled();
+        self.handle_buffer(cx);
 
         if self.read_queue.is_empty() {
             return;
         }
 
+        if self.read_eof {
+            self.handle_eof(cx);
+            return;
+        }
+
         // io call
 
-        if self.buffer.capacity() < 0x0100 && self.buffer.len() < 0x400 {
-            self.buffer.reserve(0x0400 - self.buffer.len());
-        }
+        self.reserve_buffer();
+
+        let Some(io) = &self.io else {
+            return;
+        };
 
-        let Poll::Ready(result) = self.io.poll_read_buf(&mut self.buffer, cx) else {
+        let Poll::Ready(result) = io.poll_read_buf(&mut self.buffer, cx) else {
             return;
         };
 
         match result {
-            Ok(0) => self.terminate(),
-            Ok(_) => {
-                self.handle_buffer();
+            Ok(0) => {
+                self.read_eof = true;
+                self.handle_eof(cx);
+            }
+            Ok(read) => {
+                self.on_read(read);
+                self.timers.reset_read();
+                self.handle_buffer(cx);
                 self.poll_read(cx);
             },
-            Err(err) => self.send_reader_err(err),
+            Err(err) => {
+                self.on_error(&err);
+                self.send_reader_err(err);
+            }
         }
     }
 
-    /// Check is current buffer is enough to send back to handle.
-    fn handle_buffer(&mut self) {
-        let Some(task) = self.read_queue.front_mut() else {
-            return;
-        };
+    fn reserve_buffer(&mut self) {
+        if self.buffer.capacity() < 0x0100 && self.buffer.len() < 0x400 {
+            self.buffer.reserve(0x0400 - self.buffer.len());
+        }
+    }
 
-        match task.cap {
-            None => if !self.buffer.is_empty() {
-                let data = self.buffer.split();
-                self.send_reader(data);
-            },
-            Some(remaining) => if self.buffer.len() >= remaining {
-                let data = self.buffer.split_to(remaining);
-                self.send_reader(data);
-            },
+    /// Read and route frames to streams in multiplexing mode.
+    fn poll_mux_read(&mut self, cx: &mut std::task::Context) {
+        loop {
+            let Some(mux) = &mut self.mux else {
+                return;
+            };
+
+            match mux.poll_route(&mut self.buffer, self.read_eof, cx) {
+                Poll::Ready(Ok(())) => {
+                    if self.read_eof {
+                        mux.handle_eof();
+                    }
+                    return;
+                }
+                Poll::Ready(Err(err)) => {
+                    // the stream is corrupted, no more frame can be read
+                    mux.handle_err(&err);
+                    self.on_error(&err);
+                    self.read_eof = true;
+                    return;
+                }
+                Poll::Pending if self.read_eof => {
+                    mux.handle_eof();
+                    return;
+                }
+                Poll::Pending => {}
+            }
+
+            // io call
+
+            self.reserve_buffer();
+
+            let Some(io) = &self.io else {
+                return;
+            };
+
+            let Poll::Ready(result) = io.poll_read_buf(&mut self.buffer, cx) else {
+                return;
+            };
+
+            match result {
+                Ok(0) => self.read_eof = true,
+                Ok(read) => {
+                    self.on_read(read);
+                    self.timers.reset_read();
+                }
+                Err(err) => {
+                    if let Some(mux) = &mut self.mux {
+                        mux.handle_err(&err);
+                    }
+                    self.on_error(&err);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Send current buffer to pending reads as long as the buffer is enough.
+    fn handle_buffer(&mut self, cx: &mut std::task::Context) {
+        while let Some(task) = self.read_queue.front_mut() {
+            let data = match task {
+                ReadTask::Read { cap: None, .. } if !self.buffer.is_empty() => self.buffer.split(),
+                ReadTask::Read { cap: Some(len), .. } if self.buffer.len() >= *len => {
+                    self.buffer.split_to(*len)
+                }
+                ReadTask::Read { .. } => return,
+                ReadTask::Until { delim, scanned, .. } => {
+                    match self.buffer[*scanned..].iter().position(|b| b == delim) {
+                        Some(n) => self.buffer.split_to(*scanned + n + 1),
+                        None => {
+                            *scanned = self.buffer.len();
+                            return;
+                        }
+                    }
+                }
+                ReadTask::Frame { frame, .. } => {
+                    if frame.poll_frame(&mut self.buffer, self.read_eof, cx).is_pending() {
+                        return;
+                    }
+                    self.read_queue.pop_front();
+                    continue;
+                }
+            };
+            self.send_reader(data);
+        }
+    }
+
+    /// Send the remaining buffer to all pending reads after end of stream.
+    fn handle_eof(&mut self, cx: &mut std::task::Context) {
+        loop {
+            self.handle_buffer(cx);
+            let Some(task) = self.read_queue.front() else {
+                return;
+            };
+            match task {
+                ReadTask::Read { cap: None, .. } | ReadTask::Until { .. } => {
+                    let data = self.buffer.split();
+                    self.send_reader(data);
+                }
+                ReadTask::Read { cap: Some(_), .. } | ReadTask::Frame { .. } => {
+                    self.send_reader_err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
         }
     }
 
     fn poll_write(&mut self, cx: &mut std::task::Context) {
-        let Some(task) = self.write_queue.front_mut() else {
+        let (Some(task), Some(io)) = (self.write_queue.front_mut(), &self.io) else {
             return;
         };
 
-        let Poll::Ready(result) = self.io.poll_write_all_buf(&mut task.bytes, cx) else {
+        match task {
+            WriteTask::Write { .. } if !self.write_closed && io.is_write_vectored() => {
+                let Poll::Ready(result) = self.poll_write_batch(cx) else {
+                    return;
+                };
+                if let Err(err) = result {
+                    self.complete_write(Err(err));
+                }
+            }
+            WriteTask::Write { bytes, .. } => {
+                let result = if self.write_closed {
+                    Err(io::ErrorKind::BrokenPipe.into())
+                } else {
+                    let len = bytes.len();
+                    let poll = io.poll_write_all_buf(bytes, cx);
+                    let written = len - bytes.len();
+                    if written != 0 {
+                        self.on_write(written);
+                        self.timers.reset_write();
+                    }
+                    let Poll::Ready(result) = poll else {
+                        return;
+                    };
+                    result
+                };
+                self.complete_write(result);
+            }
+            WriteTask::Error { .. } => self.complete_write(Ok(())),
+            WriteTask::Shutdown { .. } => {
+                let result = if self.write_closed {
+                    Ok(())
+                } else {
+                    let Poll::Ready(result) = io.poll_shutdown(cx) else {
+                        return;
+                    };
+                    result
+                };
+
+                self.write_closed = true;
+
+                if let Err(err) = &result {
+                    self.on_error(err);
+                }
+
+                if let Some(WriteTask::Shutdown { tx }) = self.write_queue.pop_front() {
+                    let _ = tx.send(self.take_write_err(self.written_seq).and(result));
+                }
+            }
+            WriteTask::Detach { .. } => {
+                self.detached = true;
+
+                for task in take(&mut self.read_queue) {
+                    task.send_err(io::ErrorKind::ConnectionAborted.into());
+                }
+                if let Some(mux) = &mut self.mux {
+                    mux.handle_err(&io::ErrorKind::ConnectionAborted.into());
+                }
+
+                if let Some(WriteTask::Detach { tx }) = self.write_queue.pop_front() {
+                    let _ = tx.send(self.take_write_err(self.written_seq));
+                }
+
+                return;
+            }
+        }
+
+        self.poll_write(cx);
+    }
+
+    /// Write consecutive queued writes with single vectored write.
+    ///
+    /// Fully written writes is completed, the error is returned for the front write.
+    fn poll_write_batch(&mut self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let Some(io) = &self.io else {
+            return Poll::Ready(Ok(()));
+        };
+
+        let mut slices = [io::IoSlice::new(&[]); MAX_IOV];
+        let mut cnt = 0;
+        for task in self.write_queue.iter().take(MAX_IOV) {
+            let WriteTask::Write { bytes, .. } = task else {
+                break;
+            };
+            if !bytes.is_empty() {
+                slices[cnt] = io::IoSlice::new(bytes);
+                cnt += 1;
+            }
+        }
+
+        let mut written = if cnt == 0 {
+            0
+        } else {
+            match ready!(io.poll_write_vectored(&slices[..cnt], cx)) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(written) => written,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        };
+
+        if written != 0 {
+            self.on_write(written);
+        }
+
+        while let Some(WriteTask::Write { bytes, .. }) = self.write_queue.front_mut() {
+            if bytes.len() > written {
+                bytes.advance(written);
+                self.timers.reset_write();
+                break;
+            }
+            written -= bytes.len();
+            self.complete_write(Ok(()));
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Pop the front write and report its result.
+    ///
+    /// If the front write is [`WriteTask::Error`], its error is reported instead.
+    fn complete_write(&mut self, result: io::Result<()>) {
+        let (len, ack, result) = match self.write_queue.pop_front() {
+            Some(WriteTask::Write { len, ack, .. }) => (len, ack, result),
+            Some(WriteTask::Error { err, len, ack }) => (len, ack, Err(err)),
+            Some(task) => {
+                self.write_queue.push_front(task);
+                return;
+            }
+            None => return,
+        };
+
+        self.release_write(len);
+        self.timers.reset_write();
+        if let Err(err) = &result {
+            self.on_error(err);
+        }
+        self.written_seq += 1;
+        if let Err(err) = &result {
+            self.fail_call(self.written_seq, err);
+        }
+        match (ack, result) {
+            (Some(ack), result) => {
+                let _ = ack.send(result);
+            }
+            (None, Err(err)) => self.write_errs.push_back((self.written_seq, err)),
+            (None, Ok(())) => {}
+        }
+        self.handle_sync();
+    }
+
+    /// Fail the response read of a call which its request write is failed.
+    fn fail_call(&mut self, seq: u64, err: &io::Error) {
+        let Some(i) = self.read_queue.iter().position(|task| {
+            matches!(task, ReadTask::Frame { call: Some(call), .. } if *call == seq)
+        }) else {
+            return;
+        };
+        let Some(ReadTask::Frame { mut frame, .. }) = self.read_queue.remove(i) else {
             return;
         };
+        frame.send_err(io::Error::new(err.kind(), err.to_string()));
+    }
 
-        if let Err(err) = result {
-            self.write_err = Some(err);
+    fn poll_timeout(&mut self, cx: &mut std::task::Context) {
+        if self.detached {
+            return;
         }
 
-        self.write_queue.pop_front();
-        self.poll_write(cx);
+        let Some(msg) = self.timers.poll_elapsed(!self.write_queue.is_empty(), cx) else {
+            return;
+        };
+
+        self.timed_out = true;
+        self.on_error(&timed_out(msg));
+
+        for task in take(&mut self.read_queue) {
+            task.send_err(timed_out(msg));
+        }
+        if let Some(mux) = &mut self.mux {
+            mux.handle_err(&timed_out(msg));
+        }
+        for task in take(&mut self.write_queue) {
+            match task {
+                WriteTask::Write { len, ack, .. } | WriteTask::Error { len, ack, .. } => {
+                    self.release_write(len);
+                    if let Some(ack) = ack {
+                        let _ = ack.send(Err(timed_out(msg)));
+                    }
+                }
+                WriteTask::Shutdown { tx } | WriteTask::Detach { tx } => {
+                    let _ = tx.send(Err(timed_out(msg)));
+                }
+            }
+        }
+        for (_, tx) in take(&mut self.sync_queue) {
+            let _ = tx.send(Err(timed_out(msg)));
+        }
     }
 
-    fn try_poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<()> {
-        let me = self.as_mut().get_mut();
+    fn try_poll(&mut self, cx: &mut std::task::Context) -> Poll<(IO, BytesMut)> {
+        assert!(self.io.is_some(), "`IoTask` polled after completion");
+
+        self.poll_message(cx);
+        self.poll_read(cx);
+        self.poll_write(cx);
+        self.poll_timeout(cx);
 
-        me.poll_message(cx);
-        me.poll_read(cx);
-        me.poll_write(cx);
+        if self.can_terminate() {
+            let io = self.io.take().unwrap();
+            self.terminate();
+            return Poll::Ready((io, take(&mut self.buffer)));
+        }
 
+        self.publish_stats(self.state());
         Poll::Pending
     }
 }
 
+fn not_mux() -> io::Error {
+    io::Error::other("`IoTask` is not in multiplexing mode")
+}
+
 // ===== traits =====
 
 impl<IO> Future for IoTask<IO>
 where
     IO: AsyncIoRead + AsyncIoWrite,
 {
-    type Output = ();
+    type Output = (IO, BytesMut);
 
     #[inline]
-    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
-        match self.as_mut().try_poll(cx) {
-            Poll::Ready(()) => Poll::Ready(()),
-            Poll::Pending => {
-                if self.can_terminate() {
-                    Poll::Ready(())
-                } else {
-                    Poll::Pending
-                }
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context) -> Poll<Self::Output> {
+        self.get_mut().try_poll(cx)
+    }
+}
+
+impl<IO> IoTask<IO> {
+    fn state(&self) -> TaskState {
+        if self.timed_out {
+            TaskState::TimedOut
+        } else if self.detaching {
+            TaskState::Detaching
+        } else {
+            TaskState::Running
+        }
+    }
+
+    /// Publish the statistics to handles.
+    fn publish_stats(&mut self, state: TaskState) {
+        self.stats.queued_reads =
+            self.read_queue.len() + self.mux.as_ref().map_or(0, Mux::queued_reads);
+        self.stats.read_eof = self.read_eof;
+        self.stats.write_closed = self.write_closed;
+        self.stats.state = state;
+        *self.shared_stats.lock().unwrap_or_else(PoisonError::into_inner) = self.stats.clone();
+    }
+
+    fn terminate(&mut self) {
+        // messages sent after detach is ignored, release the budget of its writes
+        for msg in self.rx.close() {
+            match msg {
+                TaskTxMessage::Write { bytes, .. }
+                | TaskTxMessage::Call { bytes, .. }
+                | TaskTxMessage::MuxWrite { bytes, .. } => self.budget.release(bytes.len()),
+                _ => {}
             }
         }
+        self.publish_stats(TaskState::Terminated);
+        if let Some(observer) = self.observer.take() {
+            observer.on_terminate(&self.stats);
+        }
+    }
+}
+
+impl<IO> Drop for IoTask<IO> {
+    fn drop(&mut self) {
+        self.budget.close();
+        if self.io.is_some() {
+            self.terminate();
+        }
     }
 }
 
 impl<IO> std::fmt::Debug for IoTask<IO> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        f.debug_struct("IoTask").finish_non_exhaustive()
+        f.debug_struct("IoTask")
+            .field("state", &self.state())
+            .field("bytes_read", &self.stats.bytes_read)
+            .field("bytes_written", &self.stats.bytes_written)
+            .field("queued_reads", &self.read_queue.len())
+            .field("queued_writes", &self.write_queue.len())
+            .field("read_eof", &self.read_eof)
+            .field("write_closed", &self.write_closed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[test]
+fn test_half_close() {
+    use std::pin::pin;
+
+    use crate::io::mock::duplex;
+    use super::IoHandle;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let (io, peer) = duplex(64);
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    // peer sends request and half close
+    assert!(peer.poll_write(b"Req", &mut cx).is_ready());
+    assert!(peer.poll_shutdown(&mut cx).is_ready());
+
+    let mut read = pin!(handle.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(read.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Req"));
+
+    // end of stream
+    let mut read = pin!(handle.read());
+    let mut read_exact = pin!(handle.read_exact(4));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(read.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b.is_empty()));
+    let err = read_exact.as_mut().poll(&mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof));
+
+    // response is still written
+    handle.write(Bytes::from_static(b"Res"));
+    let mut shutdown = pin!(handle.shutdown());
+    assert!(task.as_mut().poll(&mut cx).is_ready());
+    assert!(matches!(shutdown.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+
+    let mut buf = [0; 8];
+    assert!(matches!(peer.poll_read(&mut buf, &mut cx), Poll::Ready(Ok(3))));
+    assert_eq!(&buf[..3], b"Res");
+    assert!(matches!(peer.poll_read(&mut buf, &mut cx), Poll::Ready(Ok(0))));
+}
+
+#[test]
+fn test_detach() {
+    use std::pin::pin;
+
+    use crate::io::mock;
+    use super::Builder;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new().read(b"Foo\r\nBar").write(b"Baz").build();
+    let (handle, task) = Builder::new().max_queued_writes(1).build_handle(io);
+    let mut task = pin!(task);
+
+    let mut read = pin!(handle.read_exact(5));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(read.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Foo\r\n"));
+
+    // pending writes is completed, and buffered bytes is returned
+    handle.write(Bytes::from_static(b"Baz"));
+    let mut detach = pin!(handle.detach());
+    handle.write(Bytes::from_static(b"Ignored"));
+
+    let Poll::Ready((io, buf)) = task.as_mut().poll(&mut cx) else {
+        panic!("task is not completed");
+    };
+    assert_eq!(buf, &b"Bar"[..]);
+    assert!(matches!(detach.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+    drop(io);
+
+    // ignored write does not hold the budget
+    assert!(matches!(pin!(handle.write_ready()).poll(&mut cx), Poll::Ready(Ok(()))));
+}
+
+#[test]
+fn test_write_acked() {
+    use std::pin::pin;
+
+    use crate::io::mock;
+    use super::IoHandle;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .write(b"Foo")
+        .write_error(io::ErrorKind::BrokenPipe.into())
+        .write_error(io::ErrorKind::ConnectionReset.into())
+        .build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let mut foo = pin!(handle.write_acked(Bytes::from_static(b"Foo")));
+    let mut bar = pin!(handle.write_acked(Bytes::from_static(b"Bar")));
+    handle.write(Bytes::from_static(b"Baz"));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+
+    // error is attributed to the failed write
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+    let err = bar.as_mut().poll(&mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::BrokenPipe));
+
+    let mut sync = pin!(handle.sync());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    let err = sync.as_mut().poll(&mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::ConnectionReset));
+}
+
+#[test]
+fn test_sync() {
+    use std::pin::pin;
+
+    use crate::io::mock;
+    use super::IoHandle;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .would_block()
+        .write_error(io::ErrorKind::BrokenPipe.into())
+        .write(b"Bar")
+        .write_error(io::ErrorKind::ConnectionReset.into())
+        .build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    handle.write(Bytes::from_static(b"Foo"));
+    let mut sync1 = pin!(handle.sync());
+    handle.write(Bytes::from_static(b"Bar"));
+    handle.write(Bytes::from_static(b"Baz"));
+    let mut sync2 = pin!(handle.sync());
+
+    // sync waits for the pending write
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(sync1.as_mut().poll(&mut cx).is_pending());
+
+    // each sync receives error of its own writes
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    let err = sync1.as_mut().poll(&mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::BrokenPipe));
+    let err = sync2.as_mut().poll(&mut cx);
+    assert!(matches!(err, Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::ConnectionReset));
+
+    let mut sync = pin!(handle.sync());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(sync.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+}
+
+#[test]
+fn test_write_batch() {
+    use std::pin::pin;
+
+    use crate::io::mock;
+    use super::IoHandle;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .write_limit(4)
+        .write(b"FooB")
+        .would_block()
+        .write(b"arBaz")
+        .build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let mut foo = pin!(handle.write_acked(Bytes::from_static(b"Foo")));
+    let mut bar = pin!(handle.write_acked(Bytes::from_static(b"Bar")));
+    let mut baz = pin!(handle.write_acked(Bytes::from_static(b"Baz")));
+
+    // single write across multiple buffers, with partial progress
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+    assert!(bar.as_mut().poll(&mut cx).is_pending());
+
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(bar.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+    assert!(matches!(baz.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+}
+
+#[test]
+fn test_read_cancel() {
+    use std::pin::pin;
+
+    use crate::io::mock;
+    use super::{IoHandle, IoPoll};
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .would_block()
+        .read(b"FooBar")
+        .build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let read = handle.read_exact(3);
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    drop(read);
+
+    // data is not sent to the dropped read
+    let mut read = pin!(handle.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(read.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "FooBar"));
+
+    // polling other operation does not cancel pending read
+    let io = mock::Builder::new()
+        .would_block()
+        .read(b"Foo")
+        .build();
+    let (mut handle, task) = IoPoll::new(io);
+    let mut task = pin!(task);
+
+    assert!(handle.poll_read(&mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(handle.poll_sync(&mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(handle.poll_sync(&mut cx), Poll::Ready(Ok(()))));
+    assert!(matches!(handle.poll_read(&mut cx), Poll::Ready(Ok(b)) if b == "Foo"));
+
+    // reads does not wait for the previous read to be awaited
+    let io = mock::Builder::new().read(b"FooBar").build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let mut foo = pin!(handle.read_exact(3));
+    let mut bar = pin!(handle.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(bar.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Bar"));
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Foo"));
+
+    // different length cancels pending read, the already read data is kept
+    let io = mock::Builder::new()
+        .read(b"Foo")
+        .read(b"Bar")
+        .read(b"Baz")
+        .build();
+    let (mut handle, task) = IoPoll::new(io);
+    let mut task = pin!(task);
+
+    assert!(handle.poll_read_exact(3, &mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(handle.poll_read_exact(6, &mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(handle.poll_read_exact(6, &mut cx), Poll::Ready(Ok(b)) if b == "FooBar"));
+
+    assert!(handle.poll_read_exact(6, &mut cx).is_pending());
+    assert!(handle.poll_read(&mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(handle.poll_read(&mut cx), Poll::Ready(Ok(b)) if b == "Baz"));
+}
+
+#[test]
+fn test_read_frame() {
+    use std::pin::pin;
+
+    use crate::io::mock;
+    use super::IoHandle;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .read(b"Foo\nBa")
+        .read(b"r\r\nBaz")
+        .build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let mut foo = pin!(handle.read_until(b'\n'));
+    let mut bar = pin!(handle.read_line(16));
+    let mut baz = pin!(handle.read_until(b'\n'));
+    let mut eof = pin!(handle.read_line(16));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Foo\n"));
+    assert!(matches!(bar.as_mut().poll(&mut cx), Poll::Ready(Ok(Some(b))) if b == "Bar"));
+    assert!(matches!(baz.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Baz"));
+    assert!(matches!(eof.as_mut().poll(&mut cx), Poll::Ready(Ok(None))));
+
+    // frame of the dropped read is not decoded
+    let io = mock::Builder::new().read(b"Foo\r\nBar").build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let line = handle.read_line(16);
+    drop(line);
+
+    let mut read = pin!(handle.read());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(matches!(read.as_mut().poll(&mut cx), Poll::Ready(Ok(b)) if b == "Foo\r\nBar"));
+}
+
+#[test]
+fn test_call() {
+    use std::pin::pin;
+
+    use crate::{codec::LinesCodec, io::mock};
+    use super::IoHandle;
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .write(b"Foo\nBar\n")
+        .read(b"foo\nbar\n")
+        .build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let other = handle.clone();
+    let mut foo = pin!(handle.call(Bytes::from_static(b"Foo\n"), LinesCodec::new()));
+    let mut bar = pin!(other.call(Bytes::from_static(b"Bar\n"), LinesCodec::new()));
+    let mut sync = pin!(handle.sync());
+    // write the requests, then read the responses
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+
+    assert!(matches!(bar.as_mut().poll(&mut cx), Poll::Ready(Ok(Some(b))) if b == "bar"));
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(Some(b))) if b == "foo"));
+    assert!(matches!(sync.as_mut().poll(&mut cx), Poll::Ready(Ok(()))));
+
+    // the response read is failed when the request write is failed
+    let io = mock::Builder::new()
+        .write(b"Foo\n")
+        .write_error(io::ErrorKind::BrokenPipe.into())
+        .read(b"foo\n")
+        .build();
+    let (handle, task) = IoHandle::new(io);
+    let mut task = pin!(task);
+
+    let mut foo = pin!(handle.call(Bytes::from_static(b"Foo\n"), LinesCodec::new()));
+    let mut bar = pin!(handle.call(Bytes::from_static(b"Bar\n"), LinesCodec::new()));
+    let mut sync = pin!(handle.sync());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+
+    let Poll::Ready(Err(err)) = bar.as_mut().poll(&mut cx) else {
+        panic!("call is not failed")
+    };
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    assert!(matches!(sync.as_mut().poll(&mut cx), Poll::Ready(Err(_))));
+    assert!(matches!(foo.as_mut().poll(&mut cx), Poll::Ready(Ok(Some(b))) if b == "foo"));
+}
+
+#[test]
+fn test_stats() {
+    use std::{
+        pin::pin,
+        sync::{Mutex, atomic::{AtomicUsize, Ordering}},
+    };
+
+    use crate::io::mock;
+    use super::{Builder, Observer, Stats, TaskState};
+
+    #[derive(Default)]
+    struct Counter {
+        read: AtomicUsize,
+        written: AtomicUsize,
+        errors: AtomicUsize,
+        terminated: Mutex<Option<Stats>>,
     }
+
+    impl Observer for Arc<Counter> {
+        fn on_read(&self, len: usize) {
+            self.read.fetch_add(len, Ordering::Relaxed);
+        }
+
+        fn on_write(&self, len: usize) {
+            self.written.fetch_add(len, Ordering::Relaxed);
+        }
+
+        fn on_error(&self, _: &io::Error) {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_terminate(&self, stats: &Stats) {
+            *self.terminated.lock().unwrap() = Some(stats.clone());
+        }
+    }
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let io = mock::Builder::new()
+        .read(b"Foo")
+        .write(b"Bar")
+        .would_block()
+        .write_error(io::ErrorKind::BrokenPipe.into())
+        .build();
+    let counter = Arc::new(Counter::default());
+    let (handle, task) = Builder::new().observer(counter.clone()).build_handle(io);
+    let mut task = pin!(task);
+
+    let mut read = pin!(handle.read());
+    handle.write(Bytes::from_static(b"Bar"));
+    handle.write(Bytes::from_static(b"Baz"));
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(read.as_mut().poll(&mut cx).is_ready());
+
+    let stats = handle.stats();
+    assert_eq!(stats.bytes_read(), 3);
+    assert_eq!(stats.bytes_written(), 3);
+    assert_eq!(stats.queued_writes(), 1);
+    assert_eq!(stats.queued_write_bytes(), 3);
+    assert_eq!(stats.state(), TaskState::Running);
+    assert!(stats.last_error().is_none());
+
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    let stats = handle.stats();
+    assert_eq!(stats.queued_writes(), 0);
+    assert_eq!(stats.last_error().unwrap().kind(), io::ErrorKind::BrokenPipe);
+
+    drop(handle);
+    assert!(task.as_mut().poll(&mut cx).is_ready());
+    assert_eq!(counter.read.load(Ordering::Relaxed), 3);
+    assert_eq!(counter.written.load(Ordering::Relaxed), 3);
+    assert_eq!(counter.errors.load(Ordering::Relaxed), 1);
+    let stats = counter.terminated.lock().unwrap().take().unwrap();
+    assert_eq!(stats.state(), TaskState::Terminated);
 }
@@ -0,0 +1,185 @@
+use std::{io, time::Duration};
+#[cfg(feature = "tokio")]
+use std::{pin::Pin, task::Poll};
+#[cfg(feature = "tokio")]
+use tokio::time::{Instant, Sleep, sleep};
+
+/// Timeouts configuration.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Timeouts {
+    pub(crate) read_idle: Option<Duration>,
+    pub(crate) write_stall: Option<Duration>,
+    pub(crate) lifetime: Option<Duration>,
+}
+
+/// Running timers of [`Timeouts`].
+pub(crate) struct Timers {
+    read_idle: Timer,
+    write_stall: Timer,
+    lifetime: Timer,
+    /// Write queue is not empty on the last poll.
+    writing: bool,
+}
+
+impl Timers {
+    pub(crate) fn new(timeouts: Timeouts) -> Self {
+        Self {
+            read_idle: Timer::new(timeouts.read_idle),
+            write_stall: Timer::new(timeouts.write_stall),
+            lifetime: Timer::new(timeouts.lifetime),
+            writing: false,
+        }
+    }
+
+    /// Data is read from the io.
+    pub(crate) fn reset_read(&mut self) {
+        self.read_idle.reset();
+    }
+
+    /// Write is progressing.
+    pub(crate) fn reset_write(&mut self) {
+        self.write_stall.reset();
+    }
+
+    /// Returns the message of the first elapsed timeout.
+    ///
+    /// Write stall timer only runs when `writing` is `true`, and restarted when `writing` changed
+    /// to `true`.
+    pub(crate) fn poll_elapsed(
+        &mut self,
+        writing: bool,
+        cx: &mut std::task::Context,
+    ) -> Option<&'static str> {
+        if self.lifetime.poll_elapsed(cx) {
+            return Some("lifetime timeout elapsed");
+        }
+        if self.read_idle.poll_elapsed(cx) {
+            return Some("read idle timeout elapsed");
+        }
+        if !writing {
+            self.writing = false;
+            return None;
+        }
+        if !self.writing {
+            // the task may not be polled while idle, so the deadline is stale
+            self.writing = true;
+            self.write_stall.reset();
+        }
+        if self.write_stall.poll_elapsed(cx) {
+            return Some("write stall timeout elapsed");
+        }
+        None
+    }
+}
+
+/// The sleep is created on the first poll, so creating the task does not requires the runtime.
+#[cfg(feature = "tokio")]
+struct Timer {
+    duration: Option<Duration>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+#[cfg(feature = "tokio")]
+impl Timer {
+    fn new(duration: Option<Duration>) -> Self {
+        Self { duration, sleep: None }
+    }
+
+    fn reset(&mut self) {
+        if let (Some(duration), Some(sleep)) = (self.duration, &mut self.sleep) {
+            sleep.as_mut().reset(Instant::now() + duration);
+        }
+    }
+
+    fn poll_elapsed(&mut self, cx: &mut std::task::Context) -> bool {
+        let Some(duration) = self.duration else {
+            return false;
+        };
+        let sleep = self.sleep.get_or_insert_with(|| Box::pin(sleep(duration)));
+        matches!(sleep.as_mut().poll(cx), Poll::Ready(()))
+    }
+}
+
+/// Without tokio, timeouts cannot be configured, so the timer never elapsed.
+#[cfg(not(feature = "tokio"))]
+struct Timer;
+
+#[cfg(not(feature = "tokio"))]
+impl Timer {
+    fn new(_: Option<Duration>) -> Self {
+        Self
+    }
+
+    fn reset(&mut self) { }
+
+    fn poll_elapsed(&mut self, _: &mut std::task::Context) -> bool {
+        false
+    }
+}
+
+/// Returns [`TimedOut`][io::ErrorKind::TimedOut] error with the given message.
+pub(crate) fn timed_out(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, msg)
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn test_timeout() {
+    use bytes::Bytes;
+
+    use super::Builder;
+    use crate::io::{AsyncIoRead, mock::duplex};
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap();
+
+    rt.block_on(async {
+        // read idle
+        let (io, _peer) = duplex(4);
+        let (handle, task) = Builder::new()
+            .read_idle_timeout(Duration::from_secs(5))
+            .build_handle(io);
+        let task = tokio::spawn(task);
+
+        let start = Instant::now();
+        let err = handle.read().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+        assert!(task.await.is_ok());
+
+        // write stall
+        let (io, _peer) = duplex(4);
+        let (handle, task) = Builder::new()
+            .write_stall_timeout(Duration::from_secs(5))
+            .build_handle(io);
+        let task = tokio::spawn(task);
+
+        let mut sync = std::pin::pin!(handle.sync());
+        handle.write(Bytes::from_static(b"FooBar"));
+        let err = handle.sync().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(matches!(sync.as_mut().await, Ok(())));
+        assert!(task.await.is_ok());
+
+        // write stall timer is restarted after the write queue is idle
+        let (io, peer) = duplex(4);
+        let (handle, task) = Builder::new()
+            .write_stall_timeout(Duration::from_secs(5))
+            .build_handle(io);
+        let _task = tokio::spawn(task);
+
+        handle.write(Bytes::from_static(b"FooBar"));
+        let mut buf = [0; 4];
+        assert_eq!(AsyncIoRead::read(&peer, &mut buf).await.unwrap(), 4);
+        handle.write_acked(Bytes::from_static(b"XY")).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        let start = Instant::now();
+        let err = handle.write_acked(Bytes::from_static(b"X")).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+    });
+}
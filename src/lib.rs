@@ -8,12 +8,12 @@ mod either_map;
 pub mod slice;
 pub mod futures;
 pub mod io;
+pub mod codec;
 pub mod fmt;
 pub mod sync;
 
 #[cfg(feature = "tokio")]
 pub mod tokio;
-#[cfg(feature = "tokio")]
 pub mod io_task;
 
 // ===== Re-exports =====
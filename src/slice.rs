@@ -6,6 +6,9 @@ use bytes::{Bytes, BytesMut};
 /// This is intended to be used with [`slice_of_bytes`] to keep a slice of [`BytesMut`] and
 /// freezing it while keeping the slice without copying.
 ///
+/// When reading from [`BufReader`][crate::io::BufReader], [`BufCursor::poll_take`] can be used
+/// instead to get the shared [`Bytes`] directly.
+///
 /// # Examples
 ///
 /// ```
@@ -30,6 +33,7 @@ use bytes::{Bytes, BytesMut};
 /// ```
 ///
 /// [`BytesMut`]: bytes::BytesMut
+/// [`BufCursor::poll_take`]: crate::io::BufCursor::poll_take
 #[inline]
 pub fn range_of(buf: &[u8]) -> std::ops::Range<usize> {
     let ptr = buf.as_ptr() as usize;
@@ -222,8 +226,63 @@ pub fn slice_of(range: std::ops::Range<usize>, buf: &[u8]) -> &[u8] {
     unsafe { buf.get_unchecked(offset..offset + sub_len) }
 }
 
+// ===== Varint =====
+
+pub(crate) enum Varint<T> {
+    /// Decoded value and the encoded length.
+    Ok(T, usize),
+    Incomplete,
+    Overflow,
+}
+
+/// Decode unsigned LEB128 `u64`.
+pub(crate) fn varint_u64(buf: &[u8]) -> Varint<u64> {
+    let mut value = 0u64;
+    for (i, &b) in buf.iter().enumerate() {
+        // the 10th byte only have 1 bit left
+        if i == 9 && b > 1 {
+            return Varint::Overflow;
+        }
+        value |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            return Varint::Ok(value, i + 1);
+        }
+    }
+    Varint::Incomplete
+}
+
+/// Decode signed LEB128 `i64`.
+pub(crate) fn varint_i64(buf: &[u8]) -> Varint<i64> {
+    let mut value = 0i64;
+    for (i, &b) in buf.iter().enumerate() {
+        let shift = 7 * i;
+        // the 10th byte only have the sign bit left
+        if i == 9 && b != 0x00 && b != 0x7f {
+            return Varint::Overflow;
+        }
+        value |= ((b & 0x7f) as i64) << shift;
+        if b & 0x80 == 0 {
+            if shift + 7 < 64 && b & 0x40 != 0 {
+                value |= -1i64 << (shift + 7);
+            }
+            return Varint::Ok(value, i + 1);
+        }
+    }
+    Varint::Incomplete
+}
+
 // ===== Cursor =====
 
+macro_rules! pop_int {
+    ($($(#[$doc:meta])* $fn:ident: $ty:ty = $from:ident;)*) => {$(
+        $(#[$doc])*
+        #[inline]
+        pub fn $fn(&mut self) -> Option<$ty> {
+            self.pop_chunk_front().map(|e| <$ty>::$from(*e))
+        }
+    )*};
+}
+
 /// Raw bytes cursor.
 ///
 /// Provides an API for bytes reading, with unsafe methods that skip bounds checking.
@@ -329,6 +388,71 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    /// Try get the first byte as `i8`, and advance the cursor by `1`.
+    #[inline]
+    pub fn pop_i8(&mut self) -> Option<i8> {
+        self.pop_front().map(|e| e as i8)
+    }
+
+    pop_int! {
+        /// Try get big endian `u16`, and advance the cursor by `2`.
+        pop_u16_be: u16 = from_be_bytes;
+        /// Try get little endian `u16`, and advance the cursor by `2`.
+        pop_u16_le: u16 = from_le_bytes;
+        /// Try get big endian `u32`, and advance the cursor by `4`.
+        pop_u32_be: u32 = from_be_bytes;
+        /// Try get little endian `u32`, and advance the cursor by `4`.
+        pop_u32_le: u32 = from_le_bytes;
+        /// Try get big endian `u64`, and advance the cursor by `8`.
+        pop_u64_be: u64 = from_be_bytes;
+        /// Try get little endian `u64`, and advance the cursor by `8`.
+        pop_u64_le: u64 = from_le_bytes;
+        /// Try get big endian `i16`, and advance the cursor by `2`.
+        pop_i16_be: i16 = from_be_bytes;
+        /// Try get little endian `i16`, and advance the cursor by `2`.
+        pop_i16_le: i16 = from_le_bytes;
+        /// Try get big endian `i32`, and advance the cursor by `4`.
+        pop_i32_be: i32 = from_be_bytes;
+        /// Try get little endian `i32`, and advance the cursor by `4`.
+        pop_i32_le: i32 = from_le_bytes;
+        /// Try get big endian `i64`, and advance the cursor by `8`.
+        pop_i64_be: i64 = from_be_bytes;
+        /// Try get little endian `i64`, and advance the cursor by `8`.
+        pop_i64_le: i64 = from_le_bytes;
+    }
+
+    /// Try get unsigned LEB128 encoded `u64`, and advance the cursor by the encoded length.
+    ///
+    /// Returns [`None`] if the bytes is incomplete or the value overflows `u64`, the cursor is not
+    /// advanced.
+    #[inline]
+    pub fn pop_varint_u64(&mut self) -> Option<u64> {
+        match varint_u64(self.as_bytes()) {
+            Varint::Ok(value, len) => {
+                // SAFETY: `len` is within the remaining bytes
+                unsafe { self.advance(len) };
+                Some(value)
+            }
+            Varint::Incomplete | Varint::Overflow => None,
+        }
+    }
+
+    /// Try get signed LEB128 encoded `i64`, and advance the cursor by the encoded length.
+    ///
+    /// Returns [`None`] if the bytes is incomplete or the value overflows `i64`, the cursor is not
+    /// advanced.
+    #[inline]
+    pub fn pop_varint_i64(&mut self) -> Option<i64> {
+        match varint_i64(self.as_bytes()) {
+            Varint::Ok(value, len) => {
+                // SAFETY: `len` is within the remaining bytes
+                unsafe { self.advance(len) };
+                Some(value)
+            }
+            Varint::Incomplete | Varint::Overflow => None,
+        }
+    }
+
     /// Advance cursor, discarding the first `n`-th bytes.
     ///
     /// # Safety
@@ -471,3 +595,35 @@ fn test_cursor_pop_front() {
     assert!(cursor.pop_chunk_front::<2>().is_none());
     assert_eq!(cursor.as_bytes(), b"");
 }
+
+#[test]
+fn test_cursor_pop_int() {
+    let mut cursor = Cursor::new(b"\xff\x01\x02\x01\x02\xff\xff\xff\xfe\x01\x02\x03\x04\x05\x06\x07");
+
+    assert_eq!(cursor.pop_i8(), Some(-1));
+    assert_eq!(cursor.pop_u16_be(), Some(0x0102));
+    assert_eq!(cursor.pop_u16_le(), Some(0x0201));
+    assert_eq!(cursor.pop_i32_be(), Some(-2));
+    assert_eq!(cursor.pop_u64_be(), None);
+    assert_eq!(cursor.pop_u32_le(), Some(0x04030201));
+    assert_eq!(cursor.remaining(), 3);
+
+    // 624485 and -123456
+    let mut cursor = Cursor::new(b"\xe5\x8e\x26\xc0\xbb\x78\x80");
+
+    assert_eq!(cursor.pop_varint_u64(), Some(624485));
+    assert_eq!(cursor.pop_varint_i64(), Some(-123456));
+
+    // incomplete is not consumed
+    assert_eq!(cursor.pop_varint_u64(), None);
+    assert_eq!(cursor.remaining(), 1);
+
+    let mut cursor = Cursor::new(b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01");
+    assert_eq!(cursor.pop_varint_u64(), Some(u64::MAX));
+
+    let mut cursor = Cursor::new(b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02");
+    assert_eq!(cursor.pop_varint_u64(), None);
+
+    let mut cursor = Cursor::new(b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x7f");
+    assert_eq!(cursor.pop_varint_i64(), Some(i64::MIN));
+}
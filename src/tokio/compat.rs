@@ -0,0 +1,385 @@
+use bytes::{Buf, BytesMut};
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Poll, Wake, Waker, ready},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::poll_read;
+use crate::io::{AsyncIoRead, AsyncIoWrite};
+
+const READ_AHEAD_SIZE: usize = 0x2000;
+
+/// Adapter from tokio [`AsyncRead`] and [`AsyncWrite`] to [`AsyncIoRead`] and [`AsyncIoWrite`].
+///
+/// This allows io object that only implement tokio traits, such as TLS streams, to be used with
+/// [`BufReader`][crate::io::BufReader], [`BufCursor`][crate::io::BufCursor] or
+/// [`IoTask`][crate::io_task::IoTask].
+///
+/// The inner io is guarded by a [`Mutex`], because tokio traits requires mutable access while
+/// [`AsyncIoRead`] and [`AsyncIoWrite`] only provide shared reference.
+///
+/// # Readiness
+///
+/// Tokio traits does not have readiness notification, so [`poll_read_ready`] reads ahead into an
+/// internal buffer, which is returned by the next read.
+///
+/// For writing, if [`try_write`] returns [`WouldBlock`][io::ErrorKind::WouldBlock],
+/// [`poll_write_ready`] returns pending until the inner io wakes up the write. Otherwise, it
+/// flushes the inner io, and returns ready when the flush is complete. Prefer [`poll_write`]
+/// which registers the waker directly to the inner io.
+///
+/// [`poll_read_ready`]: AsyncIoRead::poll_read_ready
+/// [`poll_write_ready`]: AsyncIoWrite::poll_write_ready
+/// [`try_write`]: AsyncIoWrite::try_write
+/// [`poll_write`]: AsyncIoWrite::poll_write
+#[derive(Debug)]
+pub struct Compat<T> {
+    inner: Mutex<Inner<T>>,
+    write_waker: Arc<WriteWaker>,
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    io: T,
+    read_buf: BytesMut,
+    /// Read ahead found end of stream.
+    eof: bool,
+}
+
+/// Waker registered to the inner io by [`try_write`][AsyncIoWrite::try_write], which wakes up
+/// the last [`poll_write_ready`][AsyncIoWrite::poll_write_ready] caller.
+#[derive(Debug, Default)]
+struct WriteWaker {
+    state: Mutex<WriteWakerState>,
+}
+
+#[derive(Debug, Default)]
+struct WriteWakerState {
+    /// The last write would block and the inner io is not yet wakes it up.
+    blocked: bool,
+    waker: Option<Waker>,
+}
+
+impl WriteWaker {
+    fn lock(&self) -> MutexGuard<'_, WriteWakerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Wake for WriteWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut state = self.lock();
+        state.blocked = false;
+        let waker = state.waker.take();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Compat<T> {
+    /// Creates new [`Compat`].
+    #[inline]
+    pub fn new(io: T) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                io,
+                read_buf: BytesMut::new(),
+                eof: false,
+            }),
+            write_waker: Arc::default(),
+        }
+    }
+
+    /// Returns mutable reference to the inner io.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner.get_mut().unwrap_or_else(PoisonError::into_inner).io
+    }
+
+    /// Consumes the [`Compat`], returning the inner io and data that is read ahead.
+    pub fn into_parts(self) -> (T, BytesMut) {
+        let inner = self.inner.into_inner().unwrap_or_else(PoisonError::into_inner);
+        (inner.io, inner.read_buf)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Poll a write with [`WriteWaker`], returns [`WouldBlock`][io::ErrorKind::WouldBlock] if
+    /// pending.
+    fn try_write_with(
+        &self,
+        f: impl FnOnce(&mut std::task::Context) -> Poll<io::Result<usize>>,
+    ) -> io::Result<usize> {
+        // blocked before polling, in case the inner io wakes up the write immediately
+        self.write_waker.lock().blocked = true;
+        let waker = Waker::from(self.write_waker.clone());
+        match f(&mut std::task::Context::from_waker(&waker)) {
+            Poll::Ready(result) => {
+                self.write_waker.lock().blocked = false;
+                result
+            }
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    /// Returns `Some` if there is read ahead data or end of stream.
+    fn read_ahead(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if !self.read_buf.is_empty() {
+            let len = buf.len().min(self.read_buf.len());
+            buf[..len].copy_from_slice(&self.read_buf[..len]);
+            self.read_buf.advance(len);
+            return Some(len);
+        }
+        if std::mem::take(&mut self.eof) {
+            return Some(0);
+        }
+        None
+    }
+}
+
+impl<T: AsyncRead + Unpin> Inner<T> {
+    fn poll_read(&mut self, buf: &mut [u8], cx: &mut std::task::Context) -> Poll<io::Result<usize>> {
+        if let Some(read) = self.read_ahead(buf) {
+            return Poll::Ready(Ok(read));
+        }
+        let mut buf = ReadBuf::new(buf);
+        ready!(Pin::new(&mut self.io).poll_read(cx, &mut buf)?);
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncIoRead for Compat<T> {
+    fn poll_read_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let mut inner = self.lock();
+        let inner = &mut *inner;
+        if !inner.read_buf.is_empty() || inner.eof {
+            return Poll::Ready(Ok(()));
+        }
+        inner.read_buf.reserve(READ_AHEAD_SIZE);
+        if ready!(poll_read(&mut inner.read_buf, &mut inner.io, cx)?) == 0 {
+            inner.eof = true;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        match self.lock().poll_read(buf, &mut cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn try_read_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.try_read(buf),
+            None => Ok(0),
+        }
+    }
+
+    #[inline]
+    fn poll_read(&self, buf: &mut [u8], cx: &mut std::task::Context) -> Poll<io::Result<usize>> {
+        self.lock().poll_read(buf, cx)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncIoWrite for Compat<T> {
+    fn poll_write_ready(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        let mut state = self.write_waker.lock();
+        if state.blocked {
+            match &mut state.waker {
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => state.waker = Some(cx.waker().clone()),
+            }
+            return Poll::Pending;
+        }
+        drop(state);
+        Pin::new(&mut self.lock().io).poll_flush(cx)
+    }
+
+    fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.try_write_with(|cx| self.poll_write(buf, cx))
+    }
+
+    fn try_write_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.try_write_with(|cx| self.poll_write_vectored(bufs, cx))
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.lock().io.is_write_vectored()
+    }
+
+    #[inline]
+    fn poll_write(&self, buf: &[u8], cx: &mut std::task::Context) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.lock().io).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        &self,
+        bufs: &[io::IoSlice<'_>],
+        cx: &mut std::task::Context,
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.lock().io).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.lock().io).poll_shutdown(cx)
+    }
+}
+
+/// Adapter from [`AsyncIoRead`] and [`AsyncIoWrite`] to tokio [`AsyncRead`] and [`AsyncWrite`].
+///
+/// Flushing is a no-op.
+#[derive(Debug)]
+pub struct TokioCompat<T> {
+    io: T,
+}
+
+impl<T> TokioCompat<T> {
+    /// Creates new [`TokioCompat`].
+    #[inline]
+    pub fn new(io: T) -> Self {
+        Self { io }
+    }
+
+    /// Returns reference to the inner io.
+    #[inline]
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns mutable reference to the inner io.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Consumes the [`TokioCompat`], returning the inner io.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T: AsyncIoRead + Unpin> AsyncRead for TokioCompat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = ready!(self.io.poll_read(buf.initialize_unfilled(), cx)?);
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncIoWrite + Unpin> AsyncWrite for TokioCompat<T> {
+    #[inline]
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.io.poll_write(buf, cx)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.io.poll_write_vectored(bufs, cx)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        self.io.poll_shutdown(cx)
+    }
+}
+
+#[test]
+fn test_compat() {
+    use crate::io::{AsyncBufRead, BufReader};
+
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+
+    // read ahead
+    let io = Compat::new(&b"Foo"[..]);
+    assert!(io.poll_read_ready(&mut cx).is_ready());
+    let mut buf = [0u8; 2];
+    assert_eq!(io.try_read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf, b"Fo");
+    assert!(io.poll_read_ready(&mut cx).is_ready());
+    assert_eq!(io.try_read(&mut buf).unwrap(), 1);
+    assert_eq!(&buf[..1], b"o");
+    assert!(io.poll_read_ready(&mut cx).is_ready());
+    assert_eq!(io.try_read(&mut buf).unwrap(), 0);
+
+    let mut io = BufReader::new(Compat::new(&b"Foo"[..]));
+    assert!(matches!(io.poll_read_fill(&mut cx), Poll::Ready(Ok(3))));
+    assert!(matches!(io.poll_read_fill(&mut cx), Poll::Ready(Ok(0))));
+    assert_eq!(io.chunk(), b"Foo");
+
+    let io = Compat::new(Vec::new());
+    assert!(matches!(io.poll_write_all_buf(&mut &b"Foo"[..], &mut cx), Poll::Ready(Ok(()))));
+    assert_eq!(io.into_parts().0, b"Foo");
+
+    // write readiness after would block
+    struct Flag(std::sync::atomic::AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    let (io, peer) = crate::io::mock::duplex(4);
+    let io = Compat::new(TokioCompat::new(io));
+    assert_eq!(io.try_write(b"FooBar").unwrap(), 4);
+    assert_eq!(io.try_write(b"Bar").unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+    let flag = Arc::new(Flag(Default::default()));
+    let waker = Waker::from(flag.clone());
+    let mut write_cx = std::task::Context::from_waker(&waker);
+    assert!(io.poll_write_ready(&mut write_cx).is_pending());
+
+    let mut buf = [0u8; 4];
+    assert_eq!(peer.try_read(&mut buf).unwrap(), 4);
+    assert!(flag.0.load(std::sync::atomic::Ordering::Relaxed));
+    assert!(matches!(io.poll_write_ready(&mut write_cx), Poll::Ready(Ok(()))));
+    assert_eq!(io.try_write(b"Bar").unwrap(), 3);
+
+    // reverse
+    let mut io = TokioCompat::new(Compat::new(&b"Bar"[..]));
+    let mut buf = [0u8; 4];
+    let mut read_buf = ReadBuf::new(&mut buf);
+    assert!(Pin::new(&mut io).poll_read(&mut cx, &mut read_buf).is_ready());
+    assert_eq!(read_buf.filled(), b"Bar");
+}
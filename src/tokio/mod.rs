@@ -1,7 +1,8 @@
 //! Integration with [`tokio`] crate.
 mod poll;
 mod stream;
+mod compat;
 
 pub use poll::{poll_read, poll_write_all};
 pub use stream::IoStream;
-
+pub use compat::{Compat, TokioCompat};
@@ -137,6 +137,15 @@ impl AsyncIoWrite for IoStream {
             Repr::Unix(u) => AsyncWrite::is_write_vectored(u),
         }
     }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut std::task::Context) -> Poll<io::Result<()>> {
+        match &self.repr {
+            Repr::Tcp(t) => AsyncIoWrite::poll_shutdown(t, cx),
+            #[cfg(unix)]
+            Repr::Unix(u) => AsyncIoWrite::poll_shutdown(u, cx),
+        }
+    }
 }
 
 // ===== Tokio::io =====